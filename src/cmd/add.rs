@@ -1,24 +1,50 @@
-use crate::lib::cmd_base::Cmd;
+use crate::lib::arn::{parse_role_arn, validate_mfa_serial};
+use crate::lib::cache::parse_refresh_margin_seconds;
+use crate::lib::cmd_base::{show_secrets_arg, Cmd, SHOW_SECRETS_ARG_NAME};
+use crate::lib::field::{
+    merge_account_json, parse_policy_arns, parse_region, parse_session_tags, set_account_field,
+    split_list, validate_account, validate_transitive_tag_keys, ACCOUNT_FIELDS,
+};
 use crate::lib::fs::{
-    load_config, save_config, Account, AwsCliOutput, CredentialOutputTarget, MasqueradeConfig,
+    load_config, parse_duration_seconds, save_config, Account, AwsCliOutput,
+    CredentialOutputTarget, MasqueradeConfig, REDACTED_SECRET,
 };
 use crate::lib::io::{get_confirm_with_default, get_input};
+use crate::lib::policy::load_session_policy;
+use crate::lib::secret::{
+    create_secret_ref, parse_secret_ref, store_pending_mfa_secret, SecretStorage,
+};
+use crate::lib::session::{
+    validate_external_id, validate_role_session_name_template, validate_source_identity_template,
+    DEFAULT_ROLE_SESSION_NAME, DEFAULT_SOURCE_IDENTITY,
+};
+use crate::lib::sts::validate_sts_endpoint;
 use crate::lib::totp::TOTP;
-use clap::{App, ArgMatches, SubCommand};
-use regex::Regex;
-use rusoto_core::Region;
-use std::collections::HashMap;
-use std::str::FromStr;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{IsTerminal, Read};
 
 pub struct Add;
 pub const NAME: &str = "add";
 
 impl Cmd for Add {
     fn subcommand<'a, 'b>() -> App<'a, 'b> {
-        SubCommand::with_name(NAME).about("add a account")
+        SubCommand::with_name(NAME)
+            .about("add a account")
+            .arg(
+                Arg::with_name("account")
+                    .long("account-name")
+                    .short("a")
+                    .takes_value(true)
+                    .help("Name of the account"),
+            )
+            .args(&account_field_args())
+            .arg(show_secrets_arg())
     }
 
-    fn run(_args: &ArgMatches) -> Result<(), String> {
+    fn run(args: &ArgMatches) -> Result<(), String> {
+        let show_secrets = args.is_present(SHOW_SECRETS_ARG_NAME);
         let mut config = match load_config() {
             Ok(config) => config,
             Err(_) => MasqueradeConfig::new(),
@@ -26,15 +52,51 @@ impl Cmd for Add {
 
         let mut data = Account {
             source_profile: None,
+            source_account: None,
             role_arn: "".to_string(),
             mfa_arn: None,
             mfa_secret: None,
+            mfa_secret_ref: None,
+            external_id: None,
+            duration_seconds: None,
+            role_session_name: None,
+            source_identity: None,
+            session_policy: None,
+            policy_arns: None,
+            session_tags: None,
+            transitive_tag_keys: None,
+            sts_region: None,
+            sts_endpoint: None,
+            refresh_margin_seconds: None,
             credential_output: CredentialOutputTarget::SharedCredentials,
             output: None,
             region: None,
         };
 
-        let mut name = "".to_string();
+        if is_non_interactive(args) {
+            let name = match args.value_of("account") {
+                Some(name) => name.to_string(),
+                None if can_prompt(args) => input_account_name(&config, &"".to_string()),
+                None => return Err("account name is required (--account-name)".to_string()),
+            };
+            if config.accounts.contains_key(&name) {
+                return Err(format!("Account \"{}\" already exists.", name));
+            }
+            let mut data = apply_account_args(args, &name, &data)?;
+            if data.role_arn.is_empty() {
+                if !can_prompt(args) {
+                    return Err("role arn is required (--role-arn)".to_string());
+                }
+                data.role_arn = input_role_arn(&data.role_arn);
+            }
+            validate_account(&config, &name, &data)?;
+            store_pending_mfa_secret(&mut data)?;
+            println!("{}", data.to_display_string(&name, show_secrets));
+            config.accounts.insert(name, data);
+            return save_config(&config);
+        }
+
+        let mut name = args.value_of("account").unwrap_or("").to_string();
         while {
             let (input_name, input_data) = input_account_data(&config, &name, &data, true);
             data = input_data;
@@ -46,8 +108,10 @@ impl Cmd for Add {
                 "\nGenerated Account\n",
                 "\nDo you confirm add account? (y/n) [y]: ",
                 true,
+                show_secrets,
             )
         } {}
+        store_pending_mfa_secret(&mut data)?;
         config.accounts.insert(name, data);
 
         save_config(&config)
@@ -65,22 +129,127 @@ pub fn input_account_data(
     } else {
         old_name.clone()
     };
-    let source_profile = input_source_profile_name(&old_data.source_profile);
+    let source_account = input_source_account(config, &account_name, old_data);
+    let source_profile = match source_account {
+        None => input_optional(
+            "source profile name",
+            &old_data.source_profile,
+            String::clone,
+            |name| Ok(name.to_string()),
+        ),
+        Some(_) => None,
+    };
     let role_arn = input_role_arn(&old_data.role_arn);
-    let mfa_arn = input_mfa_arn(&old_data.mfa_arn);
-    let mfa_secret = match mfa_arn {
+    let mfa_arn = input_optional(
+        "mfa arn",
+        &old_data.mfa_arn,
+        String::clone,
+        validated(validate_mfa_serial),
+    );
+    let (mfa_secret, mfa_secret_ref) = match mfa_arn {
+        None => (None, None),
+        Some(_) => input_mfa_secret_with_storage(&account_name, old_data),
+    };
+    let external_id = input_optional(
+        "external id",
+        &old_data.external_id,
+        String::clone,
+        validated(validate_external_id),
+    );
+    let duration_seconds = input_optional(
+        "session duration seconds",
+        &old_data.duration_seconds,
+        i64::to_string,
+        parse_duration_seconds,
+    );
+    let role_session_name = input_optional(
+        "role session name template",
+        &old_data.role_session_name,
+        String::clone,
+        parse_role_session_name_template,
+    );
+    let source_identity = input_optional(
+        "source identity template",
+        &old_data.source_identity,
+        String::clone,
+        parse_source_identity_template,
+    );
+    let session_policy = input_optional(
+        "session policy (JSON or file://path)",
+        &old_data.session_policy,
+        String::clone,
+        validated(|policy| load_session_policy(policy).map(|_| ())),
+    );
+    let policy_arns = input_optional(
+        "policy arns (comma separated)",
+        &old_data.policy_arns,
+        |arns| arns.join(","),
+        parse_policy_arns,
+    );
+    let session_tags = input_optional(
+        "session tags (key=value, comma separated)",
+        &old_data.session_tags,
+        format_session_tags,
+        parse_session_tags,
+    );
+    let transitive_tag_keys = match &session_tags {
         None => None,
-        Some(_) => input_mfa_secret(&old_data.mfa_secret),
+        Some(tags) => input_optional(
+            "transitive tag keys (comma separated)",
+            &old_data.transitive_tag_keys,
+            |keys| keys.join(","),
+            |input| {
+                let keys = split_list(input);
+                validate_transitive_tag_keys(&keys, Some(tags))?;
+                Ok(keys)
+            },
+        ),
     };
+    let sts_region = input_optional(
+        "STS Region Name",
+        &old_data.sts_region,
+        |region| region.name().to_string(),
+        parse_region,
+    );
+    let sts_endpoint = input_optional(
+        "STS Endpoint URL",
+        &old_data.sts_endpoint,
+        String::clone,
+        validated(validate_sts_endpoint),
+    );
+    let refresh_margin_seconds = input_optional(
+        "cache refresh margin seconds",
+        &old_data.refresh_margin_seconds,
+        i64::to_string,
+        parse_refresh_margin_seconds,
+    );
     let credential_output = input_credential_output(&old_data.credential_output);
     let cli_output = input_cli_output(&old_data.output);
-    let default_region = input_default_region(&old_data.region);
+    let default_region = input_optional(
+        "Default Region Name",
+        &old_data.region,
+        |region| region.name().to_string(),
+        parse_region,
+    );
 
     let account_data = Account {
         source_profile: source_profile,
+        source_account,
         role_arn: role_arn,
         mfa_arn: mfa_arn,
         mfa_secret: mfa_secret,
+        mfa_secret_ref,
+        external_id,
+        duration_seconds,
+        role_session_name,
+        source_identity,
+        session_policy,
+        policy_arns,
+        session_tags,
+        transitive_tag_keys,
+        sts_region,
+        sts_endpoint,
+        refresh_margin_seconds,
         credential_output: credential_output,
         output: cli_output,
         region: default_region,
@@ -89,6 +258,74 @@ pub fn input_account_data(
     (account_name, account_data)
 }
 
+// Asks an optional value with the old one as the default.
+// An empty input keeps the old value unless its removal is confirmed.
+fn input_optional<T, D, P>(label: &str, old: &Option<T>, display: D, parse: P) -> Option<T>
+where
+    T: Clone,
+    D: Fn(&T) -> String,
+    P: Fn(&str) -> Result<T, String>,
+{
+    let default = old.as_ref().map(&display).unwrap_or_default();
+    loop {
+        let input = get_input(format!("{} [{}]: ", label, default));
+        if input.is_empty() {
+            let old = match old {
+                Some(old) => old,
+                None => return None,
+            };
+            match get_confirm_with_default(
+                format!("Do you remove \"{}\"? (y/n) [n]: ", default),
+                false,
+            ) {
+                Err(_) => println!("   invalid input"),
+                Ok(is_remove) => return if is_remove { None } else { Some(old.clone()) },
+            }
+            continue;
+        }
+        match parse(&input) {
+            Ok(value) => return Some(value),
+            Err(e) => println!("   {}", e),
+        }
+    }
+}
+
+// Fields which are stored as they are entered, after the validation.
+fn validated<F>(validate: F) -> impl Fn(&str) -> Result<String, String>
+where
+    F: Fn(&str) -> Result<(), String>,
+{
+    move |value| validate(value).map(|_| value.to_string())
+}
+
+// The placeholders are shown with the error, as the wizard doesn't show them before.
+fn parse_role_session_name_template(template: &str) -> Result<String, String> {
+    match validate_role_session_name_template(template) {
+        Ok(_) => Ok(template.to_string()),
+        Err(e) => Err(format!(
+            "{}\n   available placeholders: {{user}}, {{hostname}}, {{account}}, {{timestamp}}, {{uuid}} (default: {})",
+            e, DEFAULT_ROLE_SESSION_NAME
+        )),
+    }
+}
+
+fn parse_source_identity_template(template: &str) -> Result<String, String> {
+    match validate_source_identity_template(template) {
+        Ok(_) => Ok(template.to_string()),
+        Err(e) => Err(format!(
+            "{}\n   available placeholders: {{user}}, {{hostname}}, {{account}}, {{timestamp}}, {{uuid}} (e.g. {})",
+            e, DEFAULT_SOURCE_IDENTITY
+        )),
+    }
+}
+
+fn format_session_tags(tags: &BTreeMap<String, String>) -> String {
+    tags.iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
 fn input_account_name(config: &MasqueradeConfig, old_name: &String) -> String {
     loop {
         let suffix = if old_name.is_empty() {
@@ -113,43 +350,32 @@ fn input_account_name(config: &MasqueradeConfig, old_name: &String) -> String {
     }
 }
 
-fn input_source_profile_name(old_source_profile: &Option<String>) -> Option<String> {
-    let suffix = if let Some(old) = old_source_profile {
-        old.clone()
-    } else {
-        "".to_string()
-    };
-    loop {
-        let name = get_input(format!("source profile name [{}]: ", suffix));
-        return if name.is_empty() {
-            if let Some(old) = old_source_profile {
-                match get_confirm_with_default(
-                    format!("Do you remove \"{}\"? (y/n) [n]: ", old),
-                    false,
-                ) {
-                    Err(_) => {
-                        println!("   invalid input");
-                        continue;
-                    }
-                    Ok(is_remove) => {
-                        if is_remove {
-                            None
-                        } else {
-                            Some(old.clone())
-                        }
-                    }
-                }
-            } else {
-                None
+fn input_source_account(
+    config: &MasqueradeConfig,
+    account_name: &str,
+    old_data: &Account,
+) -> Option<String> {
+    input_optional(
+        "source account name",
+        &old_data.source_account,
+        String::clone,
+        |name| {
+            if !config.accounts.contains_key(name) {
+                return Err(format!("account \"{}\" does not exist.", name));
             }
-        } else {
-            Some(name)
-        };
-    }
+            let mut chained = MasqueradeConfig {
+                accounts: config.accounts.clone(),
+            };
+            let mut data = old_data.clone();
+            data.source_account = Some(name.to_string());
+            chained.accounts.insert(account_name.to_string(), data);
+            chained.resolve_chain(account_name)?;
+            Ok(name.to_string())
+        },
+    )
 }
 
 fn input_role_arn(old_role_arn: &String) -> String {
-    let re = Regex::new(r"^arn:aws:iam::\d+:role/([\u0021-\u007F]+/|)[\w+=,.@-]+$").unwrap();
     loop {
         let suffix = if old_role_arn.is_empty() {
             "".to_string()
@@ -165,72 +391,86 @@ fn input_role_arn(old_role_arn: &String) -> String {
                 arn = old_role_arn.clone();
             }
         }
-        if !re.is_match(&arn) {
-            println!("   invalid role arn!!");
+        if let Err(e) = parse_role_arn(&arn) {
+            println!("   invalid role arn!! {}", e);
             continue;
         }
         return arn;
     }
 }
 
-fn input_mfa_arn(old_mfa_arn: &Option<String>) -> Option<String> {
-    let default = if let Some(mfa) = old_mfa_arn {
-        mfa.clone()
-    } else {
-        "".to_string()
+// The old secret is shown redacted, and kept as it is when the input is empty.
+fn input_mfa_secret(old_secret: &Option<String>) -> Option<String> {
+    let redact = |_: &String| REDACTED_SECRET.to_string();
+    input_optional("mfa secret", old_secret, redact, |secret| {
+        TOTP::new(&secret.to_string())?;
+        Ok(secret.to_string())
+    })
+}
+
+// Returns the secret to store and the reference to the storage.
+// The secret is None when the secret already stored is kept.
+fn input_mfa_secret_with_storage(
+    account_name: &str,
+    old_data: &Account,
+) -> (Option<String>, Option<String>) {
+    let old_storage = match &old_data.mfa_secret_ref {
+        Some(reference) => match parse_secret_ref(reference) {
+            Ok((storage, _)) => storage,
+            Err(_) => SecretStorage::Plaintext,
+        },
+        None => SecretStorage::Plaintext,
     };
+    let storage = input_secret_storage(&old_storage);
+    if storage == SecretStorage::Plaintext {
+        return (input_mfa_secret(&old_data.mfa_secret), None);
+    }
+    if storage == old_storage {
+        return match input_stored_mfa_secret(&storage) {
+            Some(secret) => (Some(secret), old_data.mfa_secret_ref.clone()),
+            None => (None, old_data.mfa_secret_ref.clone()),
+        };
+    }
+    let secret = input_mfa_secret(&old_data.mfa_secret);
+    let reference = secret
+        .as_ref()
+        .map(|_| create_secret_ref(&storage, account_name));
+    (secret, reference)
+}
+
+fn input_secret_storage(old_storage: &SecretStorage) -> SecretStorage {
     loop {
-        let arn = get_input(format!("mfa arn [{}]: ", default));
-        if arn.is_empty() {
-            if default.is_empty() {
-                return None;
-            } else {
-                match get_confirm_with_default(
-                    format!("Do you remove \"{}\"? (y/n) [n]: ", default),
-                    false,
-                ) {
-                    Err(_) => {
-                        println!("   invalid input");
-                        continue;
-                    }
-                    Ok(is_remove) => return if is_remove { None } else { Some(default) },
-                }
-            }
-        } else {
-            return Some(arn);
+        let default = match old_storage {
+            SecretStorage::Plaintext => "0",
+            SecretStorage::Keyring => "1",
+            SecretStorage::Vault => "2",
+        };
+
+        println!("\nSelect MFA Secret Storage:");
+        println!(" [0] {}", SecretStorage::Plaintext);
+        println!(" [1] {}", SecretStorage::Keyring);
+        println!(" [2] {}", SecretStorage::Vault);
+
+        let number = get_input(format!("\n > [{}]: ", default));
+
+        if number.is_empty() {
+            return old_storage.clone();
+        }
+
+        match number.as_str() {
+            "0" => return SecretStorage::Plaintext,
+            "1" => return SecretStorage::Keyring,
+            "2" => return SecretStorage::Vault,
+            _ => println!("   Invalid Input"),
         }
     }
 }
 
-fn input_mfa_secret(old_secret: &Option<String>) -> Option<String> {
-    let default = if let Some(secret) = old_secret {
-        secret.clone()
-    } else {
-        "".to_string()
-    };
+fn input_stored_mfa_secret(storage: &SecretStorage) -> Option<String> {
     loop {
-        let mut secret = get_input(format!("mfa secret [{}]: ", default));
+        let secret = get_input(format!("mfa secret [stored in {}]: ", storage));
         if secret.is_empty() {
-            if default.is_empty() {
-                return None;
-            } else {
-                match get_confirm_with_default(
-                    format!("Do you remove \"{}\"? (y/n) [n]", default),
-                    false,
-                ) {
-                    Err(_) => {
-                        println!("   invalid input");
-                        continue;
-                    }
-                    Ok(is_remove) => {
-                        if is_remove {
-                            return None;
-                        } else {
-                            secret = default.clone()
-                        }
-                    }
-                }
-            }
+            return None;
         }
         match TOTP::new(&secret) {
             Ok(_) => return Some(secret),
@@ -246,6 +486,7 @@ fn input_credential_output(old_output: &CredentialOutputTarget) -> CredentialOut
             CredentialOutputTarget::Bash => "1",
             CredentialOutputTarget::Fish => "2",
             CredentialOutputTarget::PowerShell => "3",
+            CredentialOutputTarget::CredentialProcess => "4",
         };
 
         println!("\nSelect Credential Output Type:");
@@ -253,6 +494,7 @@ fn input_credential_output(old_output: &CredentialOutputTarget) -> CredentialOut
         println!(" [1] {}", CredentialOutputTarget::Bash);
         println!(" [2] {}", CredentialOutputTarget::Fish);
         println!(" [3] {}", CredentialOutputTarget::PowerShell);
+        println!(" [4] {}", CredentialOutputTarget::CredentialProcess);
 
         let number = get_input(format!("\n > [{}]: ", default));
 
@@ -265,6 +507,7 @@ fn input_credential_output(old_output: &CredentialOutputTarget) -> CredentialOut
             "1" => return CredentialOutputTarget::Bash,
             "2" => return CredentialOutputTarget::Fish,
             "3" => return CredentialOutputTarget::PowerShell,
+            "4" => return CredentialOutputTarget::CredentialProcess,
             _ => println!("   Invalid Input"),
         }
     }
@@ -312,35 +555,91 @@ fn input_cli_output(old_output: &Option<AwsCliOutput>) -> Option<AwsCliOutput> {
     }
 }
 
-fn input_default_region(old_region: &Option<Region>) -> Option<Region> {
-    let default = if let Some(region) = old_region {
-        region.name()
+const FROM_JSON_ARG_NAME: &str = "from_json";
+const MFA_SECRET_STORAGE_ARG_NAME: &str = "mfa_secret_storage";
+
+// Options to give the fields of the account without the wizard.
+pub fn account_field_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    let mut args: Vec<Arg> = ACCOUNT_FIELDS
+        .iter()
+        .map(|field| {
+            Arg::with_name(field.name)
+                .long(field.name)
+                .takes_value(true)
+                .value_name(field.value_name)
+                .help(field.help)
+        })
+        .collect();
+    args.push(
+        Arg::with_name(MFA_SECRET_STORAGE_ARG_NAME)
+            .long("mfa-secret-storage")
+            .takes_value(true)
+            .value_name("storage")
+            .possible_values(&["plaintext", "keyring", "vault"])
+            .help("Where the mfa secret is stored"),
+    );
+    args.push(
+        Arg::with_name(FROM_JSON_ARG_NAME)
+            .long("from-json")
+            .takes_value(true)
+            .value_name("path")
+            .help("Read the fields of the account from a JSON file (- for stdin)"),
+    );
+    args
+}
+
+pub fn is_non_interactive(args: &ArgMatches) -> bool {
+    args.is_present(FROM_JSON_ARG_NAME)
+        || args.is_present(MFA_SECRET_STORAGE_ARG_NAME)
+        || ACCOUNT_FIELDS
+            .iter()
+            .any(|field| args.is_present(field.name))
+}
+
+// Missing required values are asked only on a terminal which is not used for the JSON.
+fn can_prompt(args: &ArgMatches) -> bool {
+    std::io::stdin().is_terminal() && args.value_of(FROM_JSON_ARG_NAME) != Some("-")
+}
+
+fn read_account_json(source: &str) -> Result<String, String> {
+    let mut text = String::new();
+    let result = if source == "-" {
+        std::io::stdin().read_to_string(&mut text)
     } else {
-        ""
+        File::open(source).and_then(|mut file| file.read_to_string(&mut text))
     };
-    loop {
-        let region_name = get_input(format!("Default Region Name [{}]: ", default));
-        if region_name.is_empty() {
-            if let Some(old) = old_region {
-                match get_confirm_with_default(
-                    format!("Do you remove \"{}\"? (y/n) []: ", old.name()),
-                    false,
-                ) {
-                    Err(_) => {
-                        println!("   invalid input");
-                        continue;
-                    }
-                    Ok(is_remove) => return if is_remove { None } else { Some(old.clone()) },
-                }
-            } else {
-                return None;
-            }
+    match result {
+        Ok(_) => Ok(text),
+        Err(e) => Err(format!("failed to read JSON of account: {}", e)),
+    }
+}
+
+// The JSON is applied first and the options overwrite it.
+pub fn apply_account_args(
+    args: &ArgMatches,
+    account_name: &str,
+    old_data: &Account,
+) -> Result<Account, String> {
+    let mut data = match args.value_of(FROM_JSON_ARG_NAME) {
+        Some(source) => merge_account_json(old_data, &read_account_json(source)?)?,
+        None => old_data.clone(),
+    };
+    for field in ACCOUNT_FIELDS.iter() {
+        if let Some(value) = args.value_of(field.name) {
+            set_account_field(&mut data, field.name, value)?;
         }
-        match Region::from_str(&region_name) {
-            Ok(region) => return Some(region),
-            Err(e) => println!("   parse region error: {}", e),
+    }
+    // A new secret without the storage goes to the storage of the old one.
+    if let Some(storage) = args.value_of(MFA_SECRET_STORAGE_ARG_NAME) {
+        if data.mfa_secret.is_none() {
+            return Err("--mfa-secret-storage requires the mfa secret".to_string());
         }
+        data.mfa_secret_ref = match SecretStorage::from_str(storage)? {
+            SecretStorage::Plaintext => None,
+            storage => Some(create_secret_ref(&storage, account_name)),
+        };
     }
+    Ok(data)
 }
 
 pub fn confirm<T>(
@@ -349,6 +648,7 @@ pub fn confirm<T>(
     prefix_message: T,
     confirm_message: T,
     default: bool,
+    show_secrets: bool,
 ) -> bool
 where
     T: std::fmt::Display + Copy,
@@ -356,10 +656,10 @@ where
     loop {
         println!("{}", prefix_message);
 
-        let mut map: HashMap<String, Account> = HashMap::new();
-        map.insert(account_name.clone(), account_data.clone());
-        let json = serde_json::to_string_pretty(&map).unwrap();
-        println!("{}", json);
+        println!(
+            "{}",
+            account_data.to_display_string(account_name, show_secrets)
+        );
 
         match get_confirm_with_default(confirm_message, default) {
             Err(_) => {
@@ -1,16 +1,61 @@
+use crate::lib::arn::parse_role_arn;
+use crate::lib::cache::{
+    create_request_digest, get_cached_credentials, put_cached_credentials,
+    DEFAULT_REFRESH_MARGIN_SECONDS,
+};
 use crate::lib::cmd_base::Cmd;
 use crate::lib::fs::{
-    add_into_shared_credentials, add_shared_config, load_config, Account, CredentialOutputTarget,
+    add_into_shared_credentials, add_shared_config, load_config, parse_duration_seconds,
+    validate_duration_seconds, Account, CredentialOutputTarget,
+};
+use crate::lib::io::{get_input_on_stderr, MasqueradeOutputExt};
+use crate::lib::policy::{create_policy_descriptors, load_session_policy};
+use crate::lib::secret::resolve_mfa_secret;
+use crate::lib::session::{
+    create_session_tags, parse_session_tag, render_role_session_name, render_source_identity,
+    validate_external_id, validate_role_session_name, SessionNameContext,
+    DEFAULT_ROLE_SESSION_NAME, DEFAULT_SOURCE_IDENTITY,
+};
+use crate::lib::sts::{
+    create_sts_region, resolve_credentials, validate_sts_endpoint, SourceIdentityDispatcher,
+    STS_ENDPOINT_ARG_NAME, STS_REGION_ARG_NAME,
 };
-use crate::lib::io::{get_input, MasqueradeOutputExt};
 use crate::lib::totp::TOTP;
 use clap::{App, Arg, ArgMatches, SubCommand};
-use rusoto_core::credential::ProfileProvider;
+use rusoto_core::credential::{
+    AwsCredentials, DefaultCredentialsProvider, ProfileProvider, StaticProvider,
+};
 use rusoto_core::{HttpClient, Region};
-use rusoto_sts::{AssumeRoleRequest, AssumeRoleResponse, Sts, StsClient};
+use rusoto_sts::{
+    AssumeRoleRequest, AssumeRoleResponse, GetCallerIdentityRequest, GetCallerIdentityResponse,
+    PolicyDescriptorType, Sts, StsClient,
+};
+use std::str::FromStr;
 
 const TOKEN_ARG_NAME: &str = "token";
 const ASSUME_TYPE_ARG_NAME: &str = "assume_type";
+const DURATION_ARG_NAME: &str = "duration";
+const SESSION_NAME_ARG_NAME: &str = "session_name";
+const EXTERNAL_ID_ARG_NAME: &str = "external_id";
+const POLICY_ARG_NAME: &str = "policy";
+const POLICY_ARN_ARG_NAME: &str = "policy_arn";
+const TAG_ARG_NAME: &str = "tag";
+const SOURCE_IDENTITY_ARG_NAME: &str = "source_identity";
+const NO_CACHE_ARG_NAME: &str = "no_cache";
+const FORCE_REFRESH_ARG_NAME: &str = "force_refresh";
+const VERIFY_ARG_NAME: &str = "verify";
+// Credentials assumed with these arguments differ from the account setting, so they are not cached.
+const OVERRIDE_ARG_NAMES: [&str; 9] = [
+    DURATION_ARG_NAME,
+    SESSION_NAME_ARG_NAME,
+    EXTERNAL_ID_ARG_NAME,
+    POLICY_ARG_NAME,
+    POLICY_ARN_ARG_NAME,
+    TAG_ARG_NAME,
+    SOURCE_IDENTITY_ARG_NAME,
+    STS_REGION_ARG_NAME,
+    STS_ENDPOINT_ARG_NAME,
+];
 
 pub const NAME: &str = "assume";
 pub struct Assume;
@@ -19,21 +64,7 @@ impl Cmd for Assume {
     fn subcommand<'a, 'b>() -> App<'a, 'b> {
         SubCommand::with_name(NAME)
             .about("exec assume role")
-            .arg(
-                Arg::with_name("account")
-                    .required(true)
-                    .long("account-name")
-                    .short("a")
-                    .takes_value(true)
-                    .help("Name of the account"),
-            )
-            .arg(
-                Arg::with_name(TOKEN_ARG_NAME)
-                    .long("mfa-token")
-                    .short("t")
-                    .takes_value(true)
-                    .help("Input Mfa Token"),
-            )
+            .args(&assume_args())
             .arg(
                 Arg::with_name(ASSUME_TYPE_ARG_NAME)
                     .long("credential-output-target")
@@ -44,31 +75,227 @@ impl Cmd for Assume {
                         CredentialOutputTarget::Fish.to_str(),
                         CredentialOutputTarget::PowerShell.to_str(),
                         CredentialOutputTarget::SharedCredentials.to_str(),
+                        CredentialOutputTarget::CredentialProcess.to_str(),
                     ])
                     .help("Output Target"),
             )
+            .arg(
+                Arg::with_name(VERIFY_ARG_NAME)
+                    .long("verify")
+                    .help("Check the assumed credentials with GetCallerIdentity before output"),
+            )
     }
 
     fn run(args: &ArgMatches) -> Result<(), String> {
-        let account_name = args.value_of("account").unwrap();
-        let config = load_config()?;
-        let account_data = match config.accounts.get(account_name) {
-            None => return Err(format!("Account \"{}\" does not exist.", account_name)),
-            Some(data) => data,
-        };
+        let (account_name, account_data, result) = assume_account(args)?;
 
-        let output_target = get_credential_output_target(args, account_data)?;
+        // The identity goes to stderr because stdout may be evaluated by the shell.
+        if args.is_present(VERIFY_ARG_NAME) {
+            let identity = get_caller_identity(args, &account_data, &result)?;
+            eprintln!("{}", format_caller_identity(&identity));
+            check_caller_account(&account_data, &identity)?;
+        }
 
-        let client = create_sts_client(account_data)?;
-        let option = create_assume_role_option(args, account_data)?;
-        let result = exec_assume_role(option, &client)?;
+        let output_target = get_credential_output_target(args, &account_data)?;
 
-        output(
-            &account_name.to_string(),
-            account_data,
+        output(&account_name, &account_data, &result, &output_target)
+    }
+}
+
+pub fn assume_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("account")
+            .required(true)
+            .long("account-name")
+            .short("a")
+            .takes_value(true)
+            .help("Name of the account"),
+        Arg::with_name(TOKEN_ARG_NAME)
+            .long("mfa-token")
+            .short("t")
+            .takes_value(true)
+            .help("Input Mfa Token"),
+        Arg::with_name(DURATION_ARG_NAME)
+            .long("duration")
+            .short("d")
+            .takes_value(true)
+            .help("Session duration in seconds (900-43200)"),
+        Arg::with_name(SESSION_NAME_ARG_NAME)
+            .long("session-name")
+            .short("s")
+            .takes_value(true)
+            .help("Role session name (overrides the account template)"),
+        Arg::with_name(EXTERNAL_ID_ARG_NAME)
+            .long("external-id")
+            .short("e")
+            .takes_value(true)
+            .help("External ID required by the role's trust policy"),
+        Arg::with_name(POLICY_ARG_NAME)
+            .long("policy")
+            .takes_value(true)
+            .help("Inline session policy (JSON or file://path)"),
+        Arg::with_name(POLICY_ARN_ARG_NAME)
+            .long("policy-arn")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Managed policy ARN to use as a session policy (repeatable)"),
+        Arg::with_name(TAG_ARG_NAME)
+            .long("tag")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Session tag as key=value (repeatable)"),
+        Arg::with_name(SOURCE_IDENTITY_ARG_NAME)
+            .long("source-identity")
+            .takes_value(true)
+            .min_values(0)
+            .help("Source identity or its template (defaults to the local user)"),
+        Arg::with_name(STS_REGION_ARG_NAME)
+            .long("sts-region")
+            .takes_value(true)
+            .help("Region of STS endpoint (overrides the account setting)"),
+        Arg::with_name(STS_ENDPOINT_ARG_NAME)
+            .long("sts-endpoint")
+            .takes_value(true)
+            .help("Custom URL of STS endpoint (overrides the account setting)"),
+        Arg::with_name(NO_CACHE_ARG_NAME)
+            .long("no-cache")
+            .help("Neither read nor write the credential cache"),
+        Arg::with_name(FORCE_REFRESH_ARG_NAME)
+            .long("force-refresh")
+            .help("Assume role even if cached credentials are valid"),
+    ]
+}
+
+pub fn assume_account(args: &ArgMatches) -> Result<(String, Account, AssumeRoleResponse), String> {
+    let account_name = args.value_of("account").unwrap();
+    let config = load_config()?;
+    let chain = config.resolve_chain(account_name)?;
+    let account_data = chain.last().unwrap().1.clone();
+
+    let use_cache = !args.is_present(NO_CACHE_ARG_NAME)
+        && !OVERRIDE_ARG_NAMES.iter().any(|name| args.is_present(name));
+    let request_digest = create_request_digest(&chain);
+    if use_cache && !args.is_present(FORCE_REFRESH_ARG_NAME) {
+        let margin = account_data
+            .refresh_margin_seconds
+            .unwrap_or(DEFAULT_REFRESH_MARGIN_SECONDS);
+        // The cache is only an optimization, so a broken one doesn't stop assuming the role.
+        match get_cached_credentials(
+            account_name,
+            &account_data.role_arn,
+            &request_digest,
+            margin,
+        ) {
+            Ok(Some(result)) => return Ok((account_name.to_string(), account_data, result)),
+            Ok(None) => (),
+            Err(e) => eprintln!("warning: {}", e),
+        }
+    }
+
+    let result = assume_chain(args, &chain)?;
+
+    if use_cache {
+        // The MFA token has already been used, so the credentials are output even if caching fails.
+        if let Err(e) = put_cached_credentials(
+            account_name,
+            &account_data.role_arn,
+            &request_digest,
             &result,
-            &output_target,
-        )
+        ) {
+            eprintln!("warning: {}", e);
+        }
+    }
+
+    Ok((account_name.to_string(), account_data, result))
+}
+
+// Arguments of the command line are applied only to the last (requested) account,
+// except the STS region and endpoint, which every account is assumed through.
+fn assume_chain(
+    args: &ArgMatches,
+    chain: &[(&str, &Account)],
+) -> Result<AssumeRoleResponse, String> {
+    let empty_args = ArgMatches::default();
+    let mut credentials: Option<AwsCredentials> = None;
+    let mut result: Option<AssumeRoleResponse> = None;
+    for (index, (name, account)) in chain.iter().enumerate() {
+        let hop_args = if index + 1 == chain.len() {
+            args
+        } else {
+            &empty_args
+        };
+        let source_identity = get_source_identity(hop_args, name, account)?;
+        let region = get_sts_region(args, account)?;
+        let client = create_sts_client(account, region, credentials.take(), &source_identity)?;
+        let option = create_assume_role_option(hop_args, name, account)?;
+        let resp = exec_assume_role(option, &client)?;
+        credentials = Some(get_aws_credentials(&resp)?);
+        result = Some(resp);
+    }
+    match result {
+        Some(result) => Ok(result),
+        None => Err("no account to assume".to_string()),
+    }
+}
+
+pub fn get_caller_identity(
+    args: &ArgMatches,
+    account: &Account,
+    result: &AssumeRoleResponse,
+) -> Result<GetCallerIdentityResponse, String> {
+    let region = get_sts_region(args, account)?;
+    let client = StsClient::new_with(
+        create_http_client()?,
+        StaticProvider::from(get_aws_credentials(result)?),
+        region,
+    );
+    let mut runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => return Err(format!("failed to create async runtime: {}", e)),
+    };
+    match runtime.block_on(client.get_caller_identity(GetCallerIdentityRequest {})) {
+        Ok(resp) => Ok(resp),
+        Err(e) => Err(format!("failed to get caller identity: {}", e)),
+    }
+}
+
+pub fn format_caller_identity(identity: &GetCallerIdentityResponse) -> String {
+    let unknown = String::new();
+    format!(
+        "Account: {}\nArn: {}\nUserId: {}",
+        identity.account.as_ref().unwrap_or(&unknown),
+        identity.arn.as_ref().unwrap_or(&unknown),
+        identity.user_id.as_ref().unwrap_or(&unknown)
+    )
+}
+
+// The credentials must belong to the account of the configured role.
+pub fn check_caller_account(
+    account: &Account,
+    identity: &GetCallerIdentityResponse,
+) -> Result<(), String> {
+    let expected = parse_role_arn(&account.role_arn)?.account_id;
+    match &identity.account {
+        Some(actual) if *actual == expected => Ok(()),
+        Some(actual) => Err(format!(
+            "account mismatch: expected {} (role_arn), but got {}",
+            expected, actual
+        )),
+        None => Err("failed to verify account: no account in caller identity".to_string()),
+    }
+}
+
+fn get_aws_credentials(resp: &AssumeRoleResponse) -> Result<AwsCredentials, String> {
+    match &resp.credentials {
+        Some(c) => Ok(AwsCredentials::new(
+            c.access_key_id.clone(),
+            c.secret_access_key.clone(),
+            Some(c.session_token.clone()),
+            None,
+        )),
+        None => Err("failed to assume role: no credentials in response".to_string()),
     }
 }
 
@@ -87,54 +314,249 @@ fn exec_assume_role(
     }
 }
 
-fn create_sts_client(account: &Account) -> Result<StsClient, String> {
+fn create_sts_client(
+    account: &Account,
+    region: Region,
+    source_credentials: Option<AwsCredentials>,
+    source_identity: &Option<String>,
+) -> Result<StsClient, String> {
+    let credentials = match source_credentials {
+        Some(credentials) => credentials,
+        None => match source_identity {
+            Some(_) => resolve_source_credentials(account)?,
+            None => return create_source_sts_client(account, region),
+        },
+    };
+    let client = match source_identity {
+        Some(source_identity) => StsClient::new_with(
+            SourceIdentityDispatcher::new(
+                create_http_client()?,
+                credentials.clone(),
+                source_identity,
+            ),
+            StaticProvider::from(credentials),
+            region,
+        ),
+        None => StsClient::new_with(
+            create_http_client()?,
+            StaticProvider::from(credentials),
+            region,
+        ),
+    };
+    Ok(client)
+}
+
+fn create_source_sts_client(account: &Account, region: Region) -> Result<StsClient, String> {
     if let Some(source_profile) = &account.source_profile {
-        let http_client = match HttpClient::new() {
-            Ok(client) => client,
-            Err(e) => return Err(format!("failed to create HTTP Client: {}", e)),
-        };
-        let mut provider = match ProfileProvider::new() {
-            Ok(provider) => provider,
-            Err(e) => {
-                return Err(format!(
-                    "failed to create Profile Credential Provider: {}",
-                    e
-                ))
-            }
-        };
-        provider.set_profile(source_profile);
-        Ok(StsClient::new_with(http_client, provider, Region::UsEast1))
+        let provider = create_profile_provider(source_profile)?;
+        Ok(StsClient::new_with(create_http_client()?, provider, region))
+    } else {
+        Ok(StsClient::new(region))
+    }
+}
+
+fn resolve_source_credentials(account: &Account) -> Result<AwsCredentials, String> {
+    if let Some(source_profile) = &account.source_profile {
+        resolve_credentials(create_profile_provider(source_profile)?)
     } else {
-        Ok(StsClient::new(Region::UsEast1))
+        match DefaultCredentialsProvider::new() {
+            Ok(provider) => resolve_credentials(provider),
+            Err(e) => Err(format!("failed to create Credential Provider: {}", e)),
+        }
     }
 }
 
+// The global flags of the command line apply to every account of the chain.
+fn get_sts_region(args: &ArgMatches, account: &Account) -> Result<Region, String> {
+    let region = match args.value_of(STS_REGION_ARG_NAME) {
+        Some(name) => match Region::from_str(name) {
+            Ok(region) => Some(region),
+            Err(e) => return Err(format!("invalid sts region: {}", e)),
+        },
+        None => account.sts_region.clone(),
+    };
+    let endpoint = match args.value_of(STS_ENDPOINT_ARG_NAME) {
+        Some(endpoint) => Some(endpoint.to_string()),
+        None => account.sts_endpoint.clone(),
+    };
+    if let Some(endpoint) = &endpoint {
+        validate_sts_endpoint(endpoint)?;
+    }
+    // An invalid role_arn is left to STS to reject.
+    let partition = parse_role_arn(&account.role_arn)
+        .map(|arn| arn.partition)
+        .unwrap_or_default();
+    Ok(create_sts_region(region, endpoint, &partition))
+}
+
+fn create_http_client() -> Result<HttpClient, String> {
+    match HttpClient::new() {
+        Ok(client) => Ok(client),
+        Err(e) => Err(format!("failed to create HTTP Client: {}", e)),
+    }
+}
+
+fn create_profile_provider(source_profile: &str) -> Result<ProfileProvider, String> {
+    let mut provider = match ProfileProvider::new() {
+        Ok(provider) => provider,
+        Err(e) => {
+            return Err(format!(
+                "failed to create Profile Credential Provider: {}",
+                e
+            ))
+        }
+    };
+    provider.set_profile(source_profile);
+    Ok(provider)
+}
+
 fn create_assume_role_option(
     args: &ArgMatches,
+    account_name: &str,
     account: &Account,
 ) -> Result<AssumeRoleRequest, String> {
     let mut option = AssumeRoleRequest {
         role_arn: account.role_arn.clone(),
-        role_session_name: uuid::Uuid::new_v4().to_string(),
+        role_session_name: get_role_session_name(args, account_name, account)?,
         ..Default::default()
     };
+    option.duration_seconds = get_duration_seconds(args, account)?;
+    option.external_id = get_external_id(args, account)?;
+    option.policy = get_session_policy(args, account)?;
+    option.policy_arns = get_policy_arns(args, account)?;
+    set_session_tags(args, account, &mut option)?;
     if let Some(mfa_arn) = &account.mfa_arn {
         option.serial_number = Some(mfa_arn.clone());
-        option.token_code = Some(get_mfa_token(args, account)?);
+        option.token_code = Some(get_mfa_token(args, account_name, account)?);
     }
 
     Ok(option)
 }
 
-fn get_mfa_token(args: &ArgMatches, account: &Account) -> Result<String, String> {
+fn get_role_session_name(
+    args: &ArgMatches,
+    account_name: &str,
+    account: &Account,
+) -> Result<String, String> {
+    if let Some(name) = args.value_of(SESSION_NAME_ARG_NAME) {
+        validate_role_session_name(name)?;
+        return Ok(name.to_string());
+    }
+    let template = match &account.role_session_name {
+        Some(template) => template.as_str(),
+        None => DEFAULT_ROLE_SESSION_NAME,
+    };
+    render_role_session_name(template, &SessionNameContext::new(account_name))
+}
+
+fn get_source_identity(
+    args: &ArgMatches,
+    account_name: &str,
+    account: &Account,
+) -> Result<Option<String>, String> {
+    let template = if args.is_present(SOURCE_IDENTITY_ARG_NAME) {
+        args.value_of(SOURCE_IDENTITY_ARG_NAME)
+            .unwrap_or(DEFAULT_SOURCE_IDENTITY)
+    } else {
+        match &account.source_identity {
+            Some(template) => template.as_str(),
+            None => return Ok(None),
+        }
+    };
+    render_source_identity(template, &SessionNameContext::new(account_name)).map(Some)
+}
+
+fn get_external_id(args: &ArgMatches, account: &Account) -> Result<Option<String>, String> {
+    let external_id = match args.value_of(EXTERNAL_ID_ARG_NAME) {
+        Some(external_id) => external_id.to_string(),
+        None => match &account.external_id {
+            Some(external_id) => external_id.clone(),
+            None => return Ok(None),
+        },
+    };
+    validate_external_id(&external_id)?;
+    Ok(Some(external_id))
+}
+
+fn get_session_policy(args: &ArgMatches, account: &Account) -> Result<Option<String>, String> {
+    let source = match args.value_of(POLICY_ARG_NAME) {
+        Some(source) => source,
+        None => match &account.session_policy {
+            Some(source) => source.as_str(),
+            None => return Ok(None),
+        },
+    };
+    load_session_policy(source).map(Some)
+}
+
+fn get_policy_arns(
+    args: &ArgMatches,
+    account: &Account,
+) -> Result<Option<Vec<PolicyDescriptorType>>, String> {
+    let arns: Vec<String> = match args.values_of(POLICY_ARN_ARG_NAME) {
+        Some(values) => values.map(|v| v.to_string()).collect(),
+        None => match &account.policy_arns {
+            Some(arns) => arns.clone(),
+            None => return Ok(None),
+        },
+    };
+    if arns.is_empty() {
+        return Ok(None);
+    }
+    create_policy_descriptors(&arns).map(Some)
+}
+
+// Tags given on the command line are merged over the account's tags.
+fn set_session_tags(
+    args: &ArgMatches,
+    account: &Account,
+    option: &mut AssumeRoleRequest,
+) -> Result<(), String> {
+    let mut tags = account.session_tags.clone().unwrap_or_default();
+    if let Some(values) = args.values_of(TAG_ARG_NAME) {
+        for value in values {
+            let (key, value) = parse_session_tag(value)?;
+            tags.insert(key, value);
+        }
+    }
+    if tags.is_empty() {
+        return Ok(());
+    }
+    let transitive_tag_keys = account.transitive_tag_keys.clone().unwrap_or_default();
+    option.tags = Some(create_session_tags(&tags, &transitive_tag_keys)?);
+    if !transitive_tag_keys.is_empty() {
+        option.transitive_tag_keys = Some(transitive_tag_keys);
+    }
+    Ok(())
+}
+
+fn get_duration_seconds(args: &ArgMatches, account: &Account) -> Result<Option<i64>, String> {
+    match args.value_of(DURATION_ARG_NAME) {
+        Some(duration) => parse_duration_seconds(duration).map(Some),
+        None => match account.duration_seconds {
+            Some(duration) => validate_duration_seconds(duration).map(Some),
+            None => Ok(None),
+        },
+    }
+}
+
+fn get_mfa_token(
+    args: &ArgMatches,
+    account_name: &str,
+    account: &Account,
+) -> Result<String, String> {
     match args.value_of(TOKEN_ARG_NAME) {
         Some(token) => Ok(token.to_string()),
         None => {
-            if let Some(secret) = &account.mfa_secret {
-                let totp = TOTP::new(secret)?;
+            if let Some(secret) = resolve_mfa_secret(account)? {
+                let totp = TOTP::new(&secret)?;
                 Ok(totp.generate())
             } else {
-                Ok(get_input("\nMFA TOKEN: "))
+                // The prompt goes to stderr because stdout may be evaluated or parsed by the caller.
+                Ok(get_input_on_stderr(format!(
+                    "\nMFA TOKEN ({}): ",
+                    account_name
+                )))
             }
         }
     }
@@ -168,17 +590,52 @@ fn output(
         }
         CredentialOutputTarget::SharedCredentials => {
             let cred = assume_result.create_shared_credentials();
-            if let Some(config) = account_data.create_shared_config() {
-                add_shared_config(account_name, &config)?;
-            }
+            add_shared_config(account_name, account_data)?;
             add_into_shared_credentials(account_name, &cred)?;
 
             println!("Your new access key pair has been stored in the AWS configuration");
             println!("To use this credential, call the AWS CLI with the --profile option (e.g. aws sts get-caller-identity --profile {})", account_name);
             return Ok(());
         }
+        CredentialOutputTarget::CredentialProcess => {
+            assume_result.create_credential_process_output()
+        }
     };
 
     println!("{}", text);
     Ok(())
 }
+
+#[cfg(test)]
+fn create_caller_identity(account: Option<&str>) -> GetCallerIdentityResponse {
+    GetCallerIdentityResponse {
+        account: account.map(|a| a.to_string()),
+        arn: Some("arn:aws:sts::000000000000:assumed-role/hestia/bell".to_string()),
+        user_id: Some("AROA000000000000:bell".to_string()),
+    }
+}
+
+#[test]
+fn test_check_caller_account() {
+    let text = r#"{"roleArn": "arn:aws:iam::000000000000:role/hestia", "credentialOutput": "bash", "region": null}"#;
+    let account: Account = serde_json::from_str(text).unwrap();
+
+    assert!(check_caller_account(&account, &create_caller_identity(Some("000000000000"))).is_ok());
+    assert_eq!(
+        check_caller_account(&account, &create_caller_identity(Some("111111111111"))),
+        Err("account mismatch: expected 000000000000 (role_arn), but got 111111111111".to_string())
+    );
+    assert!(check_caller_account(&account, &create_caller_identity(None)).is_err());
+}
+
+#[test]
+fn test_format_caller_identity() {
+    assert_eq!(
+        format_caller_identity(&create_caller_identity(Some("000000000000"))),
+        "Account: 000000000000\nArn: arn:aws:sts::000000000000:assumed-role/hestia/bell\nUserId: AROA000000000000:bell"
+    );
+    assert_eq!(
+        format_caller_identity(&GetCallerIdentityResponse::default()),
+        "Account: \nArn: \nUserId: "
+    );
+}
@@ -0,0 +1,56 @@
+use crate::lib::cmd_base::Cmd;
+use crate::lib::fs::{
+    find_credentials_to_clean, load_shared_credentials, lock_shared_credentials,
+    save_shared_credentials,
+};
+use chrono::Utc;
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+const DRY_RUN_ARG_NAME: &str = "dry_run";
+const ALL_ARG_NAME: &str = "all";
+
+pub const NAME: &str = "clean";
+pub struct Clean;
+
+impl Cmd for Clean {
+    fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name(NAME)
+            .about("remove expired credentials from shared credentials")
+            .arg(
+                Arg::with_name(DRY_RUN_ARG_NAME)
+                    .long("dry-run")
+                    .help("Show the sections to remove without removing them"),
+            )
+            .arg(Arg::with_name(ALL_ARG_NAME).long("all").help(
+                "Remove all credentials written by aws-masquerade, even if they are not expired",
+            ))
+    }
+
+    fn run(args: &ArgMatches) -> Result<(), String> {
+        let is_dry_run = args.is_present(DRY_RUN_ARG_NAME);
+        let is_all = args.is_present(ALL_ARG_NAME);
+        let _lock = lock_shared_credentials()?;
+        let mut credentials = load_shared_credentials()?;
+
+        let result = find_credentials_to_clean(&credentials, Utc::now(), is_all);
+        for section in result.invalid.iter() {
+            eprintln!(
+                "warning: [{}] has an invalid expiration and is kept. Run with --all to remove it.",
+                section
+            );
+        }
+        for (section, status) in result.removed.iter() {
+            if is_dry_run {
+                println!(" would remove [{}] ({})", section, status);
+            } else {
+                credentials.remove_section(section);
+                println!(" removed [{}] ({})", section, status);
+            }
+        }
+
+        if !is_dry_run && !result.removed.is_empty() {
+            save_shared_credentials(&credentials)?;
+        }
+        Ok(())
+    }
+}
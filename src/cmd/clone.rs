@@ -0,0 +1,68 @@
+use crate::cmd::add::{account_field_args, apply_account_args};
+use crate::lib::cmd_base::{show_secrets_arg, Cmd, SHOW_SECRETS_ARG_NAME};
+use crate::lib::field::validate_account;
+use crate::lib::fs::{load_config, save_config};
+use crate::lib::secret::{
+    create_secret_ref, load_secret, parse_secret_ref, store_pending_mfa_secret,
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+pub const NAME: &str = "clone";
+pub struct CloneAccount;
+
+impl Cmd for CloneAccount {
+    fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name(NAME)
+            .about("copy a account to a new account")
+            .arg(
+                Arg::with_name("source")
+                    .required(true)
+                    .index(1)
+                    .help("Name of the account to copy"),
+            )
+            .arg(
+                Arg::with_name("destination")
+                    .required(true)
+                    .index(2)
+                    .help("Name of the new account"),
+            )
+            .args(&account_field_args())
+            .arg(show_secrets_arg())
+    }
+
+    fn run(args: &ArgMatches) -> Result<(), String> {
+        let source = args.value_of("source").unwrap();
+        let destination = args.value_of("destination").unwrap();
+        let mut config = load_config()?;
+        let mut data = match config.accounts.get(source) {
+            None => return Err(format!("Account \"{}\" does not exist.", source)),
+            Some(data) => data.clone(),
+        };
+        if destination.is_empty() {
+            return Err("account name is required.".to_string());
+        }
+        if config.accounts.contains_key(destination) {
+            return Err(format!("Account \"{}\" already exists.", destination));
+        }
+
+        // Each account owns its stored secret, so removing one does not break the other.
+        if let Some(reference) = data.mfa_secret_ref.clone() {
+            let (storage, _) = parse_secret_ref(&reference)?;
+            if data.mfa_secret.is_none() {
+                data.mfa_secret = Some(load_secret(&reference)?);
+            }
+            data.mfa_secret_ref = Some(create_secret_ref(&storage, destination));
+        }
+        // The options change the copied fields, e.g. the role arn of another environment.
+        data = apply_account_args(args, destination, &data)?;
+        validate_account(&config, destination, &data)?;
+        store_pending_mfa_secret(&mut data)?;
+
+        println!(
+            "{}",
+            data.to_display_string(destination, args.is_present(SHOW_SECRETS_ARG_NAME))
+        );
+        config.accounts.insert(destination.to_string(), data);
+        save_config(&config)
+    }
+}
@@ -1,6 +1,10 @@
-use crate::cmd::add::{confirm, input_account_data};
-use crate::lib::cmd_base::Cmd;
-use crate::lib::fs::{load_config, save_config};
+use crate::cmd::add::{
+    account_field_args, apply_account_args, confirm, input_account_data, is_non_interactive,
+};
+use crate::cmd::set::save_account;
+use crate::lib::cmd_base::{show_secrets_arg, Cmd, SHOW_SECRETS_ARG_NAME};
+use crate::lib::field::validate_account;
+use crate::lib::fs::load_config;
 use clap::{App, Arg, ArgMatches, SubCommand};
 
 pub const NAME: &str = "edit";
@@ -8,14 +12,18 @@ pub struct Edit;
 
 impl Cmd for Edit {
     fn subcommand<'a, 'b>() -> App<'a, 'b> {
-        SubCommand::with_name(NAME).about("edit a account").arg(
-            Arg::with_name("account")
-                .required(true)
-                .long("account-name")
-                .short("a")
-                .takes_value(true)
-                .help("Name of the account"),
-        )
+        SubCommand::with_name(NAME)
+            .about("edit a account")
+            .arg(
+                Arg::with_name("account")
+                    .required(true)
+                    .long("account-name")
+                    .short("a")
+                    .takes_value(true)
+                    .help("Name of the account"),
+            )
+            .args(&account_field_args())
+            .arg(show_secrets_arg())
     }
 
     fn run(args: &ArgMatches) -> Result<(), String> {
@@ -27,19 +35,28 @@ impl Cmd for Edit {
         };
 
         let name = account_name.to_string();
-        let (_, input_data) = input_account_data(&config, &name, &data, false);
-
-        return if confirm(
-            &name,
-            &input_data,
-            "\nUpdated Account\n",
-            "\nDo you confirm edit account? (y/n) [n]: ",
-            false,
-        ) {
-            config.accounts.insert(name, input_data);
-            save_config(&config)
+        let old_secret_ref = data.mfa_secret_ref.clone();
+        let show_secrets = args.is_present(SHOW_SECRETS_ARG_NAME);
+        let input_data = if is_non_interactive(args) {
+            let input_data = apply_account_args(args, &name, data)?;
+            validate_account(&config, &name, &input_data)?;
+            println!("{}", input_data.to_display_string(&name, show_secrets));
+            input_data
         } else {
-            Ok(())
+            let (_, input_data) = input_account_data(&config, &name, data, false);
+            if !confirm(
+                &name,
+                &input_data,
+                "\nUpdated Account\n",
+                "\nDo you confirm edit account? (y/n) [n]: ",
+                false,
+                show_secrets,
+            ) {
+                return Ok(());
+            }
+            input_data
         };
+
+        save_account(&mut config, &name, old_secret_ref, input_data)
     }
 }
@@ -0,0 +1,59 @@
+use crate::cmd::assume::{assume_account, assume_args};
+use crate::lib::cmd_base::Cmd;
+use crate::lib::io::MasqueradeOutputExt;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::process::Command;
+
+const COMMAND_ARG_NAME: &str = "command";
+// These would make the AWS CLI and SDKs prefer a profile over the injected credentials.
+const REMOVED_ENV_NAMES: [&str; 2] = ["AWS_PROFILE", "AWS_DEFAULT_PROFILE"];
+
+pub const NAME: &str = "exec";
+pub struct Exec;
+
+impl Cmd for Exec {
+    fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name(NAME)
+            .about("exec a command with assumed role credentials")
+            .args(&assume_args())
+            .arg(
+                Arg::with_name(COMMAND_ARG_NAME)
+                    .required(true)
+                    .multiple(true)
+                    .last(true)
+                    .help("Command and its arguments (e.g. -- aws s3 ls)"),
+            )
+    }
+
+    fn run(args: &ArgMatches) -> Result<(), String> {
+        let (_, account_data, result) = assume_account(args)?;
+
+        let mut values = args.values_of(COMMAND_ARG_NAME).unwrap();
+        let mut command = Command::new(values.next().unwrap());
+        command.args(values);
+        for name in REMOVED_ENV_NAMES.iter() {
+            command.env_remove(name);
+        }
+        command
+            .envs(result.create_environment_variables(&account_data.output, &account_data.region));
+
+        exec_command(command)
+    }
+}
+
+// On Unix the process is replaced by the command, so its exit code and signals reach the caller as is.
+#[cfg(unix)]
+fn exec_command(mut command: Command) -> Result<(), String> {
+    use std::os::unix::process::CommandExt;
+    let e = command.exec();
+    Err(format!("failed to execute command: {}", e))
+}
+
+#[cfg(not(unix))]
+fn exec_command(mut command: Command) -> Result<(), String> {
+    let status = match command.status() {
+        Ok(status) => status,
+        Err(e) => return Err(format!("failed to execute command: {}", e)),
+    };
+    std::process::exit(status.code().unwrap_or(1));
+}
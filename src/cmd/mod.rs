@@ -1,8 +1,17 @@
 pub mod add;
 pub mod assume;
+pub mod clean;
+pub mod clone;
 pub mod config_path;
 pub mod edit;
+pub mod exec;
 pub mod list;
 pub mod remove;
+pub mod rename;
+pub mod set;
+pub mod status;
+pub mod sync_profiles;
+pub mod unset;
 pub mod validate;
 pub mod view;
+pub mod whoami;
@@ -1,20 +1,36 @@
-use crate::lib::cmd_base::Cmd;
-use crate::lib::fs::{load_config, save_config};
+use crate::lib::cmd_base::{show_secrets_arg, Cmd, SHOW_SECRETS_ARG_NAME};
+use crate::lib::fs::{
+    is_generated_credentials, is_generated_profile, load_config, load_shared_config,
+    load_shared_credentials, lock_shared_config, lock_shared_credentials, save_config,
+    save_shared_config, save_shared_credentials, Account,
+};
+use crate::lib::io::get_confirm_with_default;
+use crate::lib::secret::delete_secret;
 use clap::{App, Arg, ArgMatches, SubCommand};
 
+const KEEP_AWS_PROFILES_ARG_NAME: &str = "keep_aws_profiles";
+
 pub const NAME: &str = "remove";
 pub struct Remove;
 
 impl Cmd for Remove {
     fn subcommand<'a, 'b>() -> App<'a, 'b> {
-        SubCommand::with_name(NAME).about("remove a account").arg(
-            Arg::with_name("account")
-                .required(true)
-                .long("account-name")
-                .short("a")
-                .takes_value(true)
-                .help("Name of the account"),
-        )
+        SubCommand::with_name(NAME)
+            .about("remove a account")
+            .arg(
+                Arg::with_name("account")
+                    .required(true)
+                    .long("account-name")
+                    .short("a")
+                    .takes_value(true)
+                    .help("Name of the account"),
+            )
+            .arg(
+                Arg::with_name(KEEP_AWS_PROFILES_ARG_NAME)
+                    .long("keep-aws-profiles")
+                    .help("Keep the sections of the account in the shared config and credentials"),
+            )
+            .arg(show_secrets_arg())
     }
 
     fn run(args: &ArgMatches) -> Result<(), String> {
@@ -31,11 +47,60 @@ impl Cmd for Remove {
             "Delete Account",
             "Do you confirm delete account? (y/n) [n]: ",
             false,
+            args.is_present(SHOW_SECRETS_ARG_NAME),
         ) {
+            let data = data.clone();
+            let secret_ref = data.mfa_secret_ref.clone();
             config.accounts.remove(account_name);
-            save_config(&config)
+            save_config(&config)?;
+            if let Some(reference) = secret_ref {
+                if let Err(e) = delete_secret(&reference) {
+                    eprintln!("warning: {}", e);
+                }
+            }
+            if args.is_present(KEEP_AWS_PROFILES_ARG_NAME) {
+                Ok(())
+            } else {
+                remove_aws_profiles(account_name, &data)
+            }
         } else {
             Ok(())
         };
     }
 }
+
+fn confirm_remove_section(section: &str, file: &str) -> bool {
+    loop {
+        match get_confirm_with_default(
+            format!("Do you remove [{}] from {}? (y/n) [n]: ", section, file),
+            false,
+        ) {
+            Err(_) => println!("   invalid input"),
+            Ok(is_remove) => return is_remove,
+        }
+    }
+}
+
+// The sections written by the SharedCredentials output hold the stale session token.
+// Sections of the same name written by hand (e.g. the source profile) are never removed.
+fn remove_aws_profiles(account_name: &str, data: &Account) -> Result<(), String> {
+    let _lock = lock_shared_config()?;
+    let mut shared_config = load_shared_config()?;
+    let profile = format!("profile {}", account_name);
+    if is_generated_profile(&shared_config, account_name, data)
+        && confirm_remove_section(&profile, "shared config")
+    {
+        shared_config.remove_section(&profile);
+        save_shared_config(&shared_config)?;
+    }
+
+    let _lock = lock_shared_credentials()?;
+    let mut shared_credentials = load_shared_credentials()?;
+    if is_generated_credentials(&shared_credentials, account_name)
+        && confirm_remove_section(account_name, "shared credentials")
+    {
+        shared_credentials.remove_section(account_name);
+        save_shared_credentials(&shared_credentials)?;
+    }
+    Ok(())
+}
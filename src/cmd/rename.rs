@@ -0,0 +1,139 @@
+use crate::lib::cache::rename_cached_credentials;
+use crate::lib::cmd_base::Cmd;
+use crate::lib::fs::{
+    current_program_path, is_generated_credentials, is_generated_profile, load_config,
+    load_shared_config, load_shared_credentials, lock_shared_config, lock_shared_credentials,
+    save_config, save_shared_config, save_shared_credentials, GENERATED_PROFILE_MARKER_KEY,
+};
+use crate::lib::secret::{copy_secret, delete_secret};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+pub const NAME: &str = "rename";
+pub struct Rename;
+
+impl Cmd for Rename {
+    fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name(NAME)
+            .about("rename a account")
+            .arg(
+                Arg::with_name("old")
+                    .required(true)
+                    .index(1)
+                    .help("Name of the account"),
+            )
+            .arg(
+                Arg::with_name("new")
+                    .required(true)
+                    .index(2)
+                    .help("New name of the account"),
+            )
+    }
+
+    fn run(args: &ArgMatches) -> Result<(), String> {
+        let old_name = args.value_of("old").unwrap();
+        let new_name = args.value_of("new").unwrap();
+        let mut config = load_config()?;
+        let mut data = match config.accounts.get(old_name) {
+            None => return Err(format!("Account \"{}\" does not exist.", old_name)),
+            Some(data) => data.clone(),
+        };
+        if new_name.is_empty() {
+            return Err("account name is required.".to_string());
+        }
+        if config.accounts.contains_key(new_name) {
+            return Err(format!("Account \"{}\" already exists.", new_name));
+        }
+
+        // Both shared files are checked before anything is changed.
+        let _config_lock = lock_shared_config()?;
+        let _credentials_lock = lock_shared_credentials()?;
+        let mut shared_config = load_shared_config()?;
+        let mut shared_credentials = load_shared_credentials()?;
+        let old_profile = format!("profile {}", old_name);
+        let new_profile = format!("profile {}", new_name);
+        // Sections of the same name written by hand (e.g. the source profile) are left as they are.
+        let is_profile_renamed = is_generated_profile(&shared_config, old_name, &data);
+        let is_credentials_renamed = is_generated_credentials(&shared_credentials, old_name);
+        if is_profile_renamed && shared_config.has_section(&new_profile) {
+            return Err(format!("[{}] already exists in shared config", new_profile));
+        }
+        if is_credentials_renamed && shared_credentials.has_section(new_name) {
+            return Err(format!(
+                "[{}] already exists in shared credentials",
+                new_name
+            ));
+        }
+
+        // The secret is stored under the account name, so it is moved with the account.
+        let old_secret_ref = data.mfa_secret_ref.clone();
+        if let Some(reference) = &old_secret_ref {
+            data.mfa_secret_ref = Some(copy_secret(reference, new_name)?);
+        }
+
+        config.accounts.remove(old_name);
+        config.accounts.insert(new_name.to_string(), data.clone());
+        for (name, account) in config.accounts.iter_mut() {
+            if account.source_account.as_deref() == Some(old_name) {
+                account.source_account = Some(new_name.to_string());
+                println!(" updated source account of \"{}\"", name);
+            }
+        }
+        if let Err(e) = save_config(&config) {
+            // The copied secret is not referred to by the saved config.
+            if let Some(reference) = &data.mfa_secret_ref {
+                if let Err(e) = delete_secret(reference) {
+                    eprintln!("warning: {}", e);
+                }
+            }
+            return Err(e);
+        }
+        println!(" renamed account \"{}\" -> \"{}\"", old_name, new_name);
+        // The config refers to the copied secret from here.
+        if let Some(reference) = old_secret_ref {
+            if let Err(e) = delete_secret(&reference) {
+                eprintln!("warning: {}", e);
+            }
+        }
+
+        if is_profile_renamed && shared_config.rename_section(&old_profile, &new_profile) {
+            // The generated credential_process profile refers to the account by name.
+            if shared_config
+                .get(&new_profile, GENERATED_PROFILE_MARKER_KEY)
+                .is_some()
+            {
+                shared_config.set_section(
+                    &new_profile,
+                    &data.create_credential_process_config(new_name, &current_program_path()),
+                );
+            }
+            save_shared_config(&shared_config)?;
+            println!(" renamed [{}] -> [{}]", old_profile, new_profile);
+        }
+        if is_credentials_renamed && shared_credentials.rename_section(old_name, new_name) {
+            save_shared_credentials(&shared_credentials)?;
+            println!(
+                " renamed [{}] -> [{}] in shared credentials",
+                old_name, new_name
+            );
+        }
+
+        if !is_profile_renamed && shared_config.has_section(&old_profile) {
+            println!(
+                " kept [{}], which was not written by aws-masquerade",
+                old_profile
+            );
+        }
+        if !is_credentials_renamed && shared_credentials.has_section(old_name) {
+            println!(
+                " kept [{}] in shared credentials, which was not written by aws-masquerade",
+                old_name
+            );
+        }
+
+        // The account is already renamed, and the cached credentials are just assumed again.
+        if let Err(e) = rename_cached_credentials(old_name, new_name) {
+            eprintln!("warning: {}", e);
+        }
+        Ok(())
+    }
+}
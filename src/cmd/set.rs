@@ -0,0 +1,104 @@
+use crate::lib::cmd_base::{show_secrets_arg, Cmd, SHOW_SECRETS_ARG_NAME};
+use crate::lib::diff::diff_lines;
+use crate::lib::field::{set_account_field, validate_account, ACCOUNT_FIELDS};
+use crate::lib::fs::{load_config, save_config, Account, MasqueradeConfig};
+use crate::lib::secret::{delete_secret, store_pending_mfa_secret};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+pub const FIELD_ARG_NAME: &str = "field";
+const VALUE_ARG_NAME: &str = "value";
+
+pub const NAME: &str = "set";
+pub struct Set;
+
+impl Cmd for Set {
+    fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name(NAME)
+            .about("set a field of a account")
+            .arg(
+                Arg::with_name("account")
+                    .required(true)
+                    .long("account-name")
+                    .short("a")
+                    .takes_value(true)
+                    .help("Name of the account"),
+            )
+            .arg(field_arg())
+            .arg(
+                Arg::with_name(VALUE_ARG_NAME)
+                    .required(true)
+                    .index(2)
+                    .help("Value of the field (given in the same form as the wizard)"),
+            )
+            .arg(show_secrets_arg())
+    }
+
+    fn run(args: &ArgMatches) -> Result<(), String> {
+        let field = args.value_of(FIELD_ARG_NAME).unwrap();
+        let value = args.value_of(VALUE_ARG_NAME).unwrap();
+        update_account(args, |account| set_account_field(account, field, value))
+    }
+}
+
+pub fn field_arg<'a, 'b>() -> Arg<'a, 'b> {
+    let names: Vec<&str> = ACCOUNT_FIELDS.iter().map(|field| field.name).collect();
+    Arg::with_name(FIELD_ARG_NAME)
+        .required(true)
+        .index(1)
+        .possible_values(&names)
+        .help("Name of the field")
+}
+
+// Prints the diff of the account and saves it.
+pub fn update_account<F>(args: &ArgMatches, update: F) -> Result<(), String>
+where
+    F: FnOnce(&mut Account) -> Result<(), String>,
+{
+    let account_name = args.value_of("account").unwrap();
+    let show_secrets = args.is_present(SHOW_SECRETS_ARG_NAME);
+    let mut config = load_config()?;
+    let old_data = match config.accounts.get(account_name) {
+        None => return Err(format!("Account \"{}\" does not exist.", account_name)),
+        Some(data) => data.clone(),
+    };
+
+    let mut data = old_data.clone();
+    update(&mut data)?;
+    validate_account(&config, account_name, &data)?;
+
+    // The redacted text can't tell a changed secret, so the accounts themselves are compared.
+    if serde_json::to_value(&old_data).ok() == serde_json::to_value(&data).ok() {
+        println!("no changes");
+        return Ok(());
+    }
+    let old_text = old_data.to_display_string(account_name, show_secrets);
+    let new_text = data.to_display_string(account_name, show_secrets);
+    if old_text == new_text {
+        println!("secrets are changed (use --show-secrets to show them)");
+    } else {
+        for line in diff_lines(&old_text, &new_text) {
+            println!("{}", line);
+        }
+    }
+
+    save_account(&mut config, account_name, old_data.mfa_secret_ref, data)
+}
+
+// The secret of the account is moved to its storage, and the old one is deleted if it is replaced.
+pub fn save_account(
+    config: &mut MasqueradeConfig,
+    account_name: &str,
+    old_secret_ref: Option<String>,
+    mut data: Account,
+) -> Result<(), String> {
+    store_pending_mfa_secret(&mut data)?;
+    let is_secret_moved = old_secret_ref != data.mfa_secret_ref;
+    config.accounts.insert(account_name.to_string(), data);
+    save_config(config)?;
+    if let (true, Some(reference)) = (is_secret_moved, old_secret_ref) {
+        if let Err(e) = delete_secret(&reference) {
+            eprintln!("warning: {}", e);
+        }
+    }
+    Ok(())
+}
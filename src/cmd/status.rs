@@ -0,0 +1,181 @@
+use crate::lib::cmd_base::Cmd;
+use crate::lib::fs::{
+    find_masquerade_credentials, load_config, load_shared_credentials, MasqueradeConfig,
+    PRINCIPAL_ARN_KEY,
+};
+use crate::lib::ini::IniDocument;
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde::Serialize;
+
+const JSON_ARG_NAME: &str = "json";
+
+pub const NAME: &str = "status";
+pub struct Status;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountStatus {
+    account: String,
+    has_credentials: bool,
+    principal_arn: Option<String>,
+    expiration: Option<String>,
+    expires_in_seconds: Option<i64>,
+}
+
+impl Cmd for Status {
+    fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name(NAME)
+            .about("show credentials of accounts in shared credentials")
+            .arg(
+                Arg::with_name(JSON_ARG_NAME)
+                    .long("json")
+                    .help("Print the status as JSON"),
+            )
+    }
+
+    fn run(args: &ArgMatches) -> Result<(), String> {
+        let config = load_config()?;
+        let credentials = load_shared_credentials()?;
+        let statuses = create_account_statuses(&config, &credentials, Utc::now());
+
+        if args.is_present(JSON_ARG_NAME) {
+            println!("{}", serde_json::to_string_pretty(&statuses).unwrap());
+            return Ok(());
+        }
+
+        let width = statuses.iter().map(|s| s.account.len()).max().unwrap_or(0);
+        for status in statuses.iter() {
+            println!(
+                " {:<width$}  {:<20}  {}",
+                status.account,
+                format_lifetime(status),
+                status.principal_arn.as_deref().unwrap_or("-"),
+                width = width
+            );
+        }
+        Ok(())
+    }
+}
+
+// Accounts are matched to the sections of the same name written by aws-masquerade.
+fn create_account_statuses(
+    config: &MasqueradeConfig,
+    credentials: &IniDocument,
+    now: DateTime<Utc>,
+) -> Vec<AccountStatus> {
+    let found = find_masquerade_credentials(credentials);
+    config
+        .accounts
+        .keys()
+        .map(|name| {
+            let section = found.iter().find(|(section, _)| section == name);
+            let expiration = section.and_then(|(_, expiration)| *expiration);
+            AccountStatus {
+                account: name.clone(),
+                has_credentials: section.is_some(),
+                principal_arn: section.and_then(|_| credentials.get(name, PRINCIPAL_ARN_KEY)),
+                expiration: expiration.map(|e| e.to_rfc3339_opts(SecondsFormat::Secs, true)),
+                expires_in_seconds: expiration.map(|e| (e - now).num_seconds()),
+            }
+        })
+        .collect()
+}
+
+fn format_lifetime(status: &AccountStatus) -> String {
+    match (status.has_credentials, status.expires_in_seconds) {
+        (false, _) => "no credentials".to_string(),
+        (true, None) => "unknown expiration".to_string(),
+        (true, Some(seconds)) if seconds <= 0 => "expired".to_string(),
+        (true, Some(seconds)) => format!("expires in {}", format_duration(seconds)),
+    }
+}
+
+fn format_duration(seconds: i64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+#[test]
+fn test_format_duration() {
+    assert_eq!(format_duration(45), "45s");
+    assert_eq!(format_duration(299), "4m 59s");
+    assert_eq!(format_duration(3600), "1h 00m");
+    assert_eq!(format_duration(43199), "11h 59m");
+}
+
+#[test]
+fn test_create_account_statuses() {
+    let text = r#"{"accounts": {
+        "bell": {"roleArn": "arn:aws:iam::000000000000:role/bell", "credentialOutput": "SharedCredentials", "region": null},
+        "hestia": {"roleArn": "arn:aws:iam::000000000000:role/hestia", "credentialOutput": "SharedCredentials", "region": null},
+        "loki": {"roleArn": "arn:aws:iam::000000000000:role/loki", "credentialOutput": "SharedCredentials", "region": null},
+        "welf": {"roleArn": "arn:aws:iam::000000000000:role/welf", "credentialOutput": "SharedCredentials", "region": null}
+    }}"#;
+    let config: MasqueradeConfig = serde_json::from_str(text).unwrap();
+    let credentials = IniDocument::parse(
+        "[bell]
+x_security_token_expires = someday
+
+[hestia]
+x_principal_arn = arn:aws:sts::000000000000:assumed-role/hestia/bell
+x_security_token_expires = 2020-01-01T01:30:00Z
+
+[loki]
+x_principal_arn = arn:aws:sts::000000000000:assumed-role/loki/bell
+x_security_token_expires = 2020-01-01T00:00:00Z
+
+[welf]
+aws_access_key_id = AKIA0000000000000000
+",
+    );
+    let now = DateTime::parse_from_rfc3339("2020-01-01T01:00:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+
+    let statuses = create_account_statuses(&config, &credentials, now);
+    let lifetimes: Vec<String> = statuses.iter().map(format_lifetime).collect();
+    assert_eq!(
+        lifetimes,
+        vec![
+            "unknown expiration",
+            "expires in 30m 00s",
+            "expired",
+            "no credentials"
+        ]
+    );
+    assert_eq!(statuses[0].principal_arn, None);
+    assert_eq!(
+        statuses[1].principal_arn.as_deref(),
+        Some("arn:aws:sts::000000000000:assumed-role/hestia/bell")
+    );
+    assert_eq!(statuses[2].expires_in_seconds, Some(-3600));
+    assert!(!statuses[3].has_credentials);
+
+    assert_eq!(
+        serde_json::to_value(&statuses[1]).unwrap(),
+        serde_json::json!({
+            "account": "hestia",
+            "hasCredentials": true,
+            "principalArn": "arn:aws:sts::000000000000:assumed-role/hestia/bell",
+            "expiration": "2020-01-01T01:30:00Z",
+            "expiresInSeconds": 1800
+        })
+    );
+    assert_eq!(
+        serde_json::to_value(&statuses[3]).unwrap(),
+        serde_json::json!({
+            "account": "welf",
+            "hasCredentials": false,
+            "principalArn": null,
+            "expiration": null,
+            "expiresInSeconds": null
+        })
+    );
+}
@@ -0,0 +1,51 @@
+use crate::lib::cmd_base::Cmd;
+use crate::lib::fs::{
+    current_program_path, load_config, load_shared_config, lock_shared_config, save_shared_config,
+    sync_credential_process_profiles,
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+const FORCE_ARG_NAME: &str = "force";
+
+pub const NAME: &str = "sync-profiles";
+pub struct SyncProfiles;
+
+impl Cmd for SyncProfiles {
+    fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name(NAME)
+            .about("write credential_process profiles into shared config")
+            .arg(
+                Arg::with_name(FORCE_ARG_NAME)
+                    .long("force")
+                    .help("Write into the profiles of the same name which were not generated"),
+            )
+    }
+
+    fn run(args: &ArgMatches) -> Result<(), String> {
+        let config = load_config()?;
+        let _lock = lock_shared_config()?;
+        let mut shared_config = load_shared_config()?;
+
+        let result = sync_credential_process_profiles(
+            &mut shared_config,
+            &config,
+            &current_program_path(),
+            args.is_present(FORCE_ARG_NAME),
+        );
+        save_shared_config(&shared_config)?;
+
+        for name in result.synced.iter() {
+            println!(" synced [profile {}]", name);
+        }
+        for name in result.removed.iter() {
+            println!(" removed [profile {}]", name);
+        }
+        for name in result.skipped.iter() {
+            eprintln!(
+                "warning: skipped [profile {}], which was not generated by aws-masquerade (use --force to overwrite)",
+                name
+            );
+        }
+        Ok(())
+    }
+}
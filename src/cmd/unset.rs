@@ -0,0 +1,29 @@
+use crate::cmd::set::{field_arg, update_account, FIELD_ARG_NAME};
+use crate::lib::cmd_base::{show_secrets_arg, Cmd};
+use crate::lib::field::unset_account_field;
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+pub const NAME: &str = "unset";
+pub struct Unset;
+
+impl Cmd for Unset {
+    fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name(NAME)
+            .about("unset a field of a account")
+            .arg(
+                Arg::with_name("account")
+                    .required(true)
+                    .long("account-name")
+                    .short("a")
+                    .takes_value(true)
+                    .help("Name of the account"),
+            )
+            .arg(field_arg())
+            .arg(show_secrets_arg())
+    }
+
+    fn run(args: &ArgMatches) -> Result<(), String> {
+        let field = args.value_of(FIELD_ARG_NAME).unwrap();
+        update_account(args, |account| unset_account_field(account, field))
+    }
+}
@@ -1,16 +1,71 @@
+use crate::lib::arn::{parse_role_arn, validate_mfa_serial};
 use crate::lib::cmd_base::Cmd;
 use crate::lib::fs::load_config;
-use clap::{App, ArgMatches, SubCommand};
+use crate::lib::permission::{
+    find_loose_permission, fix_permission, get_private_files, PRIVATE_FILE_MODE,
+};
+use crate::lib::secret::parse_secret_ref;
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+const FIX_PERMISSIONS_ARG_NAME: &str = "fix_permissions";
 
 pub const NAME: &str = "validate";
 pub struct Validate;
 
 impl Cmd for Validate {
     fn subcommand<'a, 'b>() -> App<'a, 'b> {
-        SubCommand::with_name(NAME).about("validate config")
+        SubCommand::with_name(NAME).about("validate config").arg(
+            Arg::with_name(FIX_PERMISSIONS_ARG_NAME)
+                .long("fix-permissions")
+                .help("Make files containing secrets readable only by the owner"),
+        )
     }
 
-    fn run(_: &ArgMatches) -> Result<(), String> {
-        load_config().map(|_| ())
+    fn run(args: &ArgMatches) -> Result<(), String> {
+        validate_permissions(args.is_present(FIX_PERMISSIONS_ARG_NAME))?;
+
+        let config = load_config()?;
+        for (name, account) in config.accounts.iter() {
+            if let Err(e) = parse_role_arn(&account.role_arn) {
+                return Err(format!("Account \"{}\": {}", name, e));
+            }
+            if let Some(mfa_arn) = &account.mfa_arn {
+                if let Err(e) = validate_mfa_serial(mfa_arn) {
+                    return Err(format!("Account \"{}\": {}", name, e));
+                }
+            }
+            if let Some(reference) = &account.mfa_secret_ref {
+                if let Err(e) = parse_secret_ref(reference) {
+                    return Err(format!("Account \"{}\": {}", name, e));
+                }
+            }
+            config.resolve_chain(name)?;
+        }
+        Ok(())
+    }
+}
+
+fn validate_permissions(is_fix: bool) -> Result<(), String> {
+    for path in get_private_files() {
+        let mode = match find_loose_permission(&path) {
+            Some(mode) => mode,
+            None => continue,
+        };
+        if is_fix {
+            fix_permission(&path)?;
+            eprintln!(
+                "fixed permissions of {}: {:o} -> {:o}",
+                path.display(),
+                mode,
+                PRIVATE_FILE_MODE
+            );
+        } else {
+            eprintln!(
+                "warning: {} is accessible by group or others ({:o}). Run with --fix-permissions to fix it.",
+                path.display(),
+                mode
+            );
+        }
     }
+    Ok(())
 }
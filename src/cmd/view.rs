@@ -1,21 +1,23 @@
-use crate::lib::cmd_base::Cmd;
-use crate::lib::fs::{load_config, Account};
+use crate::lib::cmd_base::{show_secrets_arg, Cmd, SHOW_SECRETS_ARG_NAME};
+use crate::lib::fs::load_config;
 use clap::{App, Arg, ArgMatches, SubCommand};
-use std::collections::HashMap;
 
 pub const NAME: &str = "view";
 pub struct View;
 
 impl Cmd for View {
     fn subcommand<'a, 'b>() -> App<'a, 'b> {
-        SubCommand::with_name(NAME).about("view a account").arg(
-            Arg::with_name("account")
-                .required(true)
-                .long("account-name")
-                .short("a")
-                .takes_value(true)
-                .help("Name of the account"),
-        )
+        SubCommand::with_name(NAME)
+            .about("view a account")
+            .arg(
+                Arg::with_name("account")
+                    .required(true)
+                    .long("account-name")
+                    .short("a")
+                    .takes_value(true)
+                    .help("Name of the account"),
+            )
+            .arg(show_secrets_arg())
     }
 
     fn run(args: &ArgMatches) -> Result<(), String> {
@@ -25,10 +27,10 @@ impl Cmd for View {
             None => return Err(format!("Account \"{}\" does not exist.", account_name)),
             Some(data) => data,
         };
-        let mut map: HashMap<&str, &Account> = HashMap::new();
-        map.insert(account_name, account_data);
-        let text = serde_json::to_string_pretty(&map).unwrap();
-        println!("{}", text);
+        println!(
+            "{}",
+            account_data.to_display_string(account_name, args.is_present(SHOW_SECRETS_ARG_NAME))
+        );
 
         Ok(())
     }
@@ -0,0 +1,24 @@
+use crate::cmd::assume::{
+    assume_account, assume_args, check_caller_account, format_caller_identity, get_caller_identity,
+};
+use crate::lib::cmd_base::Cmd;
+use clap::{App, ArgMatches, SubCommand};
+
+pub const NAME: &str = "whoami";
+pub struct Whoami;
+
+impl Cmd for Whoami {
+    fn subcommand<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name(NAME)
+            .about("show the caller identity of the assumed role")
+            .args(&assume_args())
+    }
+
+    fn run(args: &ArgMatches) -> Result<(), String> {
+        let (_, account_data, result) = assume_account(args)?;
+
+        let identity = get_caller_identity(args, &account_data, &result)?;
+        println!("{}", format_caller_identity(&identity));
+        check_caller_account(&account_data, &identity)
+    }
+}
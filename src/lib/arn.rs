@@ -0,0 +1,179 @@
+use regex::Regex;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+const PARTITIONS: [&str; 7] = [
+    "aws",
+    "aws-cn",
+    "aws-us-gov",
+    "aws-iso",
+    "aws-iso-b",
+    "aws-iso-e",
+    "aws-iso-f",
+];
+const MFA_RESOURCE_TYPES: [&str; 3] = ["mfa", "u2f", "sms-mfa"];
+
+lazy_static! {
+    static ref RE_ACCOUNT_ID: Regex = Regex::new(r"^\d{12}$").unwrap();
+    static ref RE_PATH: Regex = Regex::new(r"^/([!-.0-~]+/)*$").unwrap();
+    static ref RE_NAME: Regex = Regex::new(r"^[A-Za-z0-9_+=,.@-]+$").unwrap();
+    static ref RE_MFA_SERIAL: Regex = Regex::new(r"^[A-Za-z0-9_+=/:,.@-]{9,256}$").unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arn {
+    pub partition: String,
+    pub service: String,
+    pub region: String,
+    pub account_id: String,
+    pub resource_type: String,
+    pub path: String,
+    pub name: String,
+}
+
+impl Arn {
+    // Parses "arn:partition:service:region:account-id:resource-type/path/name".
+    pub fn parse(text: &str) -> Result<Arn, String> {
+        let parts: Vec<&str> = text.splitn(6, ':').collect();
+        if parts.len() != 6 || parts[0] != "arn" {
+            return Err(format!("invalid arn: {}", text));
+        }
+        let partition = parts[1];
+        if !PARTITIONS.contains(&partition) {
+            return Err(format!(
+                "unknown partition \"{}\" in arn: {}",
+                partition, text
+            ));
+        }
+        let resource = parts[5];
+        let (resource_type, path_and_name) = match resource.find('/') {
+            Some(index) => (&resource[..index], &resource[index..]),
+            None => return Err(format!("invalid resource in arn: {}", text)),
+        };
+        let index = path_and_name.rfind('/').unwrap();
+        let path = &path_and_name[..=index];
+        let name = &path_and_name[index + 1..];
+        if !RE_PATH.is_match(path) {
+            return Err(format!("invalid path in arn: {}", text));
+        }
+        if name.is_empty() || name.len() > 128 || !RE_NAME.is_match(name) {
+            return Err(format!("invalid resource name in arn: {}", text));
+        }
+        Ok(Arn {
+            partition: partition.to_string(),
+            service: parts[2].to_string(),
+            region: parts[3].to_string(),
+            account_id: parts[4].to_string(),
+            resource_type: resource_type.to_string(),
+            path: path.to_string(),
+            name: name.to_string(),
+        })
+    }
+
+    fn expect_iam(&self, resource_types: &[&str], aws_managed: bool) -> Result<(), String> {
+        if self.service != "iam" {
+            return Err(format!("not an iam arn: {}", self));
+        }
+        if !self.region.is_empty() {
+            return Err(format!("iam arn must not have a region: {}", self));
+        }
+        let is_aws_managed = aws_managed && self.account_id == "aws";
+        if !is_aws_managed && !RE_ACCOUNT_ID.is_match(&self.account_id) {
+            return Err(format!("invalid account id in arn: {}", self));
+        }
+        if !resource_types.contains(&self.resource_type.as_str()) {
+            return Err(format!(
+                "resource type of arn must be {}: {}",
+                resource_types.join(" or "),
+                self
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Display for Arn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "arn:{}:{}:{}:{}:{}{}{}",
+            self.partition,
+            self.service,
+            self.region,
+            self.account_id,
+            self.resource_type,
+            self.path,
+            self.name
+        )
+    }
+}
+
+pub fn parse_role_arn(text: &str) -> Result<Arn, String> {
+    let arn = Arn::parse(text)?;
+    arn.expect_iam(&["role"], false)?;
+    if arn.name.len() > 64 {
+        return Err(format!("role name must be at most 64 characters: {}", text));
+    }
+    Ok(arn)
+}
+
+pub fn parse_policy_arn(text: &str) -> Result<Arn, String> {
+    let arn = Arn::parse(text)?;
+    arn.expect_iam(&["policy"], true)?;
+    Ok(arn)
+}
+
+// Hardware MFA devices are identified by a plain serial number instead of an arn.
+pub fn validate_mfa_serial(text: &str) -> Result<(), String> {
+    if text.starts_with("arn:") {
+        let arn = Arn::parse(text)?;
+        arn.expect_iam(&MFA_RESOURCE_TYPES, false)
+    } else if RE_MFA_SERIAL.is_match(text) {
+        Ok(())
+    } else {
+        Err(format!("invalid mfa serial number: {}", text))
+    }
+}
+
+#[test]
+fn test_parse_role_arn() {
+    let actual = parse_role_arn("arn:aws:iam::000000000000:role/hestia").unwrap();
+    assert_eq!(actual.partition, "aws");
+    assert_eq!(actual.account_id, "000000000000");
+    assert_eq!(actual.path, "/");
+    assert_eq!(actual.name, "hestia");
+
+    let text = "arn:aws-cn:iam::111111111111:role/familia/hestia/bell-cranel";
+    let actual = parse_role_arn(text).unwrap();
+    assert_eq!(actual.partition, "aws-cn");
+    assert_eq!(actual.resource_type, "role");
+    assert_eq!(actual.path, "/familia/hestia/");
+    assert_eq!(actual.name, "bell-cranel");
+    assert_eq!(actual.to_string(), text);
+
+    assert!(parse_role_arn("arn:aws-us-gov:iam::222222222222:role/loki").is_ok());
+    assert!(parse_role_arn("arn:aws:iam::000000000000:user/bell").is_err());
+    assert!(parse_role_arn("arn:aws:iam::00000:role/hestia").is_err());
+    assert!(parse_role_arn("arn:orario:iam::000000000000:role/hestia").is_err());
+    assert!(parse_role_arn("arn:aws:s3:::000000000000:role/hestia").is_err());
+    assert!(parse_role_arn("arn:aws:iam::000000000000:role/").is_err());
+    assert!(parse_role_arn("hestia").is_err());
+}
+
+#[test]
+fn test_validate_mfa_serial() {
+    assert!(validate_mfa_serial("arn:aws:iam::000000000000:mfa/bell").is_ok());
+    assert!(validate_mfa_serial("arn:aws-cn:iam::000000000000:mfa/path/bell").is_ok());
+    assert!(validate_mfa_serial("arn:aws:iam::000000000000:u2f/user/bell/key-ABCDEF").is_ok());
+    assert!(validate_mfa_serial("arn:aws:iam::000000000000:sms-mfa/bell").is_ok());
+    assert!(validate_mfa_serial("GAHT12345678").is_ok());
+    assert!(validate_mfa_serial("arn:aws:iam::000000000000:role/bell").is_err());
+    assert!(validate_mfa_serial("bell").is_err());
+}
+
+#[test]
+fn test_parse_policy_arn() {
+    assert!(parse_policy_arn("arn:aws:iam::aws:policy/ReadOnlyAccess").is_ok());
+    assert!(parse_policy_arn("arn:aws-cn:iam::000000000000:policy/path/to/Policy").is_ok());
+    assert!(parse_policy_arn("arn:aws:iam::aws:role/ReadOnlyAccess").is_err());
+}
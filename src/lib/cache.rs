@@ -0,0 +1,280 @@
+use crate::lib::dirs::MASQUERADE_PATH;
+use crate::lib::fs::{lock_cache, write_private_file_atomically, Account, MAX_DURATION_SECONDS};
+use crate::lib::permission::create_private_dir_all;
+use chrono::{DateTime, Duration, Utc};
+use data_encoding::HEXLOWER;
+use ring::digest::{digest, SHA256};
+use rusoto_sts::{AssumeRoleResponse, AssumedRoleUser, Credentials};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const DEFAULT_REFRESH_MARGIN_SECONDS: i64 = 300;
+// Settings which change neither the request nor the source credentials of assume role.
+const UNDIGESTED_ACCOUNT_KEYS: [&str; 6] = [
+    "mfaSecret",
+    "mfaSecretRef",
+    "refreshMarginSeconds",
+    "credentialOutput",
+    "output",
+    "region",
+];
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedCredentials {
+    pub role_arn: String,
+    // Entries written before the digest was introduced have an empty one, so they are never reused.
+    #[serde(default)]
+    pub request_digest: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: String,
+    pub expiration: String,
+    pub assumed_role_arn: String,
+    pub assumed_role_id: String,
+}
+
+impl CachedCredentials {
+    pub fn new(
+        role_arn: &str,
+        request_digest: &str,
+        response: &AssumeRoleResponse,
+    ) -> Option<CachedCredentials> {
+        let credentials = response.credentials.as_ref()?;
+        let user = response.assumed_role_user.as_ref()?;
+        Some(CachedCredentials {
+            role_arn: role_arn.to_string(),
+            request_digest: request_digest.to_string(),
+            access_key_id: credentials.access_key_id.clone(),
+            secret_access_key: credentials.secret_access_key.clone(),
+            session_token: credentials.session_token.clone(),
+            expiration: credentials.expiration.clone(),
+            assumed_role_arn: user.arn.clone(),
+            assumed_role_id: user.assumed_role_id.clone(),
+        })
+    }
+
+    pub fn to_response(&self) -> AssumeRoleResponse {
+        AssumeRoleResponse {
+            assumed_role_user: Some(AssumedRoleUser {
+                arn: self.assumed_role_arn.clone(),
+                assumed_role_id: self.assumed_role_id.clone(),
+            }),
+            credentials: Some(Credentials {
+                access_key_id: self.access_key_id.clone(),
+                secret_access_key: self.secret_access_key.clone(),
+                session_token: self.session_token.clone(),
+                expiration: self.expiration.clone(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        parse_expiration(&self.expiration)
+    }
+
+    // Credentials are reused only while they stay valid for longer than the margin,
+    // and only for the role and the settings they were issued for.
+    pub fn is_fresh(
+        &self,
+        role_arn: &str,
+        request_digest: &str,
+        now: DateTime<Utc>,
+        margin_seconds: i64,
+    ) -> bool {
+        if self.role_arn != role_arn || self.request_digest != request_digest {
+            return false;
+        }
+        match self.expires_at() {
+            Some(expiration) => now + Duration::seconds(margin_seconds) < expiration,
+            None => false,
+        }
+    }
+}
+
+// Digest of the settings of every account in the chain, so that the cached credentials
+// are not reused after any of them is changed (e.g. tags, policy or the source account).
+pub fn create_request_digest(chain: &[(&str, &Account)]) -> String {
+    let accounts: Vec<(&str, serde_json::Value)> = chain
+        .iter()
+        .map(|(name, account)| {
+            let mut value = serde_json::to_value(account).unwrap();
+            if let Some(map) = value.as_object_mut() {
+                for key in UNDIGESTED_ACCOUNT_KEYS.iter() {
+                    map.remove(*key);
+                }
+            }
+            (*name, value)
+        })
+        .collect();
+    let text = serde_json::to_string(&accounts).unwrap();
+    HEXLOWER.encode(digest(&SHA256, text.as_bytes()).as_ref())
+}
+
+// STS returns the expiration in RFC 3339 (e.g. 2020-01-01T00:00:00Z).
+pub fn parse_expiration(text: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(text)
+        .ok()
+        .map(|e| e.with_timezone(&Utc))
+}
+
+pub fn parse_refresh_margin_seconds(text: &str) -> Result<i64, String> {
+    match text.parse() {
+        Ok(margin) => validate_refresh_margin_seconds(margin),
+        Err(_) => Err(format!("refresh margin must be a number: {}", text)),
+    }
+}
+
+pub fn validate_refresh_margin_seconds(margin: i64) -> Result<i64, String> {
+    if (0..=MAX_DURATION_SECONDS).contains(&margin) {
+        Ok(margin)
+    } else {
+        Err(format!(
+            "refresh margin must be between 0 and {}: {}",
+            MAX_DURATION_SECONDS, margin
+        ))
+    }
+}
+
+pub fn load_cache() -> Result<HashMap<String, CachedCredentials>, String> {
+    let path = MASQUERADE_PATH.cache();
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => return Err(format!("failed to read credential cache: {}", e)),
+    };
+    match serde_json::from_str(&text) {
+        Ok(cache) => Ok(cache),
+        Err(e) => Err(format!("failed to parse credential cache: {}", e)),
+    }
+}
+
+pub fn save_cache(cache: &HashMap<String, CachedCredentials>) -> Result<(), String> {
+    let path = MASQUERADE_PATH.cache();
+    let dir = path.parent().unwrap();
+    match create_private_dir_all(dir) {
+        Ok(_) => (),
+        Err(e) => return Err(format!("failed to create config directory: {}", e)),
+    };
+    let text = match serde_json::to_string_pretty(cache) {
+        Ok(text) => text,
+        Err(e) => return Err(format!("failed to serialize credential cache: {}", e)),
+    };
+    match write_private_file_atomically(path, &text) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("failed to write credential cache: {}", e)),
+    }
+}
+
+pub fn get_cached_credentials(
+    account_name: &str,
+    role_arn: &str,
+    request_digest: &str,
+    margin_seconds: i64,
+) -> Result<Option<AssumeRoleResponse>, String> {
+    let cache = load_cache()?;
+    Ok(cache
+        .get(account_name)
+        .filter(|c| c.is_fresh(role_arn, request_digest, Utc::now(), margin_seconds))
+        .map(|c| c.to_response()))
+}
+
+pub fn put_cached_credentials(
+    account_name: &str,
+    role_arn: &str,
+    request_digest: &str,
+    response: &AssumeRoleResponse,
+) -> Result<(), String> {
+    let cached = match CachedCredentials::new(role_arn, request_digest, response) {
+        Some(cached) => cached,
+        None => return Ok(()),
+    };
+    let _lock = lock_cache()?;
+    // A broken cache is replaced, as it holds only credentials which can be assumed again.
+    let mut cache = load_cache().unwrap_or_default();
+    let now = Utc::now();
+    cache.retain(|_, c| c.expires_at().is_some_and(|e| now < e));
+    cache.insert(account_name.to_string(), cached);
+    save_cache(&cache)
+}
+
+pub fn rename_cached_credentials(account_name: &str, new_name: &str) -> Result<(), String> {
+    let _lock = lock_cache()?;
+    let mut cache = load_cache()?;
+    match cache.remove(account_name) {
+        Some(cached) => {
+            cache.insert(new_name.to_string(), cached);
+            save_cache(&cache)
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+fn create_cached_credentials(expiration: &str) -> CachedCredentials {
+    CachedCredentials {
+        role_arn: "arn:aws:iam::000000000000:role/hestia".to_string(),
+        request_digest: "Falna".to_string(),
+        access_key_id: "Adventurer (Bell Cranel)".to_string(),
+        secret_access_key: "The Blade of a God (Hestia Knife)".to_string(),
+        session_token: "The Weak (Supporter)".to_string(),
+        expiration: expiration.to_string(),
+        assumed_role_arn: "arn:aws:sts::000000000000:assumed-role/hestia/bell".to_string(),
+        assumed_role_id: "AROA000000000000:bell".to_string(),
+    }
+}
+
+#[test]
+fn test_cached_credentials_is_fresh() {
+    let cached = create_cached_credentials("2020-07-20T12:00:00Z");
+    let role_arn = "arn:aws:iam::000000000000:role/hestia";
+    let now = DateTime::parse_from_rfc3339("2020-07-20T11:50:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+
+    assert!(cached.is_fresh(role_arn, "Falna", now, 300));
+    assert!(!cached.is_fresh(role_arn, "Falna", now, 600));
+    assert!(!cached.is_fresh(role_arn, "Falna", now + Duration::hours(1), 0));
+    assert!(!cached.is_fresh("arn:aws:iam::000000000000:role/loki", "Falna", now, 300));
+    assert!(!cached.is_fresh(role_arn, "Status", now, 300));
+    assert!(!create_cached_credentials("Monster Festival").is_fresh(role_arn, "Falna", now, 0));
+}
+
+#[test]
+fn test_cached_credentials_to_response() {
+    let cached = create_cached_credentials("2020-07-20T12:00:00Z");
+    let response = cached.to_response();
+    let actual = CachedCredentials::new(&cached.role_arn, &cached.request_digest, &response);
+    assert_eq!(actual, Some(cached));
+}
+
+#[test]
+fn test_create_request_digest() {
+    let text = r#"{"sourceProfile": "default", "roleArn": "arn:aws:iam::000000000000:role/hestia", "credentialOutput": "bash", "region": null}"#;
+    let account: Account = serde_json::from_str(text).unwrap();
+    let expected = create_request_digest(&[("hestia", &account)]);
+
+    let mut changed = account.clone();
+    changed.output = Some(crate::lib::fs::AwsCliOutput::Json);
+    changed.refresh_margin_seconds = Some(0);
+    assert_eq!(create_request_digest(&[("hestia", &changed)]), expected);
+
+    changed.external_id = Some("Familia".to_string());
+    assert_ne!(create_request_digest(&[("hestia", &changed)]), expected);
+    assert_ne!(
+        create_request_digest(&[("guild", &account), ("hestia", &account)]),
+        expected
+    );
+}
+
+#[test]
+fn test_parse_refresh_margin_seconds() {
+    assert_eq!(parse_refresh_margin_seconds("0"), Ok(0));
+    assert_eq!(parse_refresh_margin_seconds("300"), Ok(300));
+    assert!(parse_refresh_margin_seconds("-1").is_err());
+    assert!(parse_refresh_margin_seconds("43201").is_err());
+    assert!(parse_refresh_margin_seconds("five").is_err());
+}
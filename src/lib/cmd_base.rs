@@ -1,6 +1,14 @@
-use clap::{App, ArgMatches};
+use clap::{App, Arg, ArgMatches};
 
 pub trait Cmd {
     fn subcommand<'a, 'b>() -> App<'a, 'b>;
     fn run(args: &ArgMatches) -> Result<(), String>;
 }
+
+pub const SHOW_SECRETS_ARG_NAME: &str = "show_secrets";
+
+pub fn show_secrets_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name(SHOW_SECRETS_ARG_NAME)
+        .long("show-secrets")
+        .help("Show secrets (e.g. mfa secret) instead of redacting them")
+}
@@ -0,0 +1,54 @@
+// Line diff by the longest common subsequence. Lines are prefixed with "- ", "+ " or "  ".
+pub fn diff_lines(old: &str, new: &str) -> Vec<String> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lengths[i][j] is the length of the LCS of old[i..] and new[j..].
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(format!("  {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            lines.push(format!("- {}", old[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|line| format!("- {}", line)));
+    lines.extend(new[j..].iter().map(|line| format!("+ {}", line)));
+    lines
+}
+
+#[test]
+fn test_diff_lines() {
+    let old = "{\n  \"region\": null,\n  \"output\": \"json\"\n}";
+    let new = "{\n  \"region\": \"ap-northeast-1\",\n  \"output\": \"json\"\n}";
+    assert_eq!(
+        diff_lines(old, new),
+        vec![
+            "  {",
+            "-   \"region\": null,",
+            "+   \"region\": \"ap-northeast-1\",",
+            "    \"output\": \"json\"",
+            "  }",
+        ]
+    );
+    assert_eq!(diff_lines("a\nb", "a\nb\nc"), vec!["  a", "  b", "+ c"]);
+    assert_eq!(diff_lines("a\nb", "b"), vec!["- a", "  b"]);
+}
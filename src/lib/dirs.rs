@@ -3,6 +3,8 @@ use std::path::{Path, PathBuf};
 
 pub struct MasqueradePath {
     config_file: PathBuf,
+    cache_file: PathBuf,
+    vault_file: PathBuf,
     shared_credential_file: PathBuf,
     shared_config_file: PathBuf,
 }
@@ -11,6 +13,14 @@ fn get_config_path() -> Option<PathBuf> {
     dirs::home_dir().map(|p| p.join(".config/aws-masquerade/config.json"))
 }
 
+fn get_cache_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|p| p.join(".config/aws-masquerade/cache.json"))
+}
+
+fn get_vault_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|p| p.join(".config/aws-masquerade/vault.json"))
+}
+
 fn get_shared_credential_path() -> Option<PathBuf> {
     std::env::var("AWS_SHARED_CREDENTIALS_FILE")
         .ok()
@@ -30,10 +40,14 @@ fn get_shared_config_path() -> Option<PathBuf> {
 impl MasqueradePath {
     fn new() -> Option<MasqueradePath> {
         let config = get_config_path()?;
+        let cache = get_cache_path()?;
+        let vault = get_vault_path()?;
         let shared_credential = get_shared_credential_path()?;
         let shared_config = get_shared_config_path()?;
         Some(MasqueradePath {
             config_file: config,
+            cache_file: cache,
+            vault_file: vault,
             shared_credential_file: shared_credential,
             shared_config_file: shared_config,
         })
@@ -42,6 +56,12 @@ impl MasqueradePath {
     pub fn config(&self) -> &Path {
         &self.config_file
     }
+    pub fn cache(&self) -> &Path {
+        &self.cache_file
+    }
+    pub fn vault(&self) -> &Path {
+        &self.vault_file
+    }
     pub fn shared_credentials(&self) -> &Path {
         &self.shared_credential_file
     }
@@ -0,0 +1,403 @@
+use crate::lib::arn::{parse_role_arn, validate_mfa_serial};
+use crate::lib::cache::{parse_refresh_margin_seconds, validate_refresh_margin_seconds};
+use crate::lib::fs::{
+    parse_duration_seconds, validate_duration_seconds, Account, AwsCliOutput,
+    CredentialOutputTarget, MasqueradeConfig,
+};
+use crate::lib::policy::{load_session_policy, validate_policy_arn};
+use crate::lib::secret::parse_secret_ref;
+use crate::lib::session::{
+    parse_session_tag, validate_external_id, validate_role_session_name_template,
+    validate_session_tag, validate_source_identity_template,
+};
+use crate::lib::sts::validate_sts_endpoint;
+use crate::lib::totp::TOTP;
+use rusoto_core::Region;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+pub struct AccountField {
+    // name of the argument and the option on the command line, e.g. --role-arn
+    pub name: &'static str,
+    pub value_name: &'static str,
+    pub help: &'static str,
+}
+
+pub const ACCOUNT_FIELDS: &[AccountField] = &[
+    AccountField {
+        name: "source-account",
+        value_name: "account",
+        help: "Another masquerade account to assume this role from (role chaining)",
+    },
+    AccountField {
+        name: "source-profile",
+        value_name: "profile",
+        help: "Source profile for assuming role",
+    },
+    AccountField {
+        name: "role-arn",
+        value_name: "arn",
+        help: "Target iam role arn",
+    },
+    AccountField {
+        name: "mfa-arn",
+        value_name: "arn",
+        help: "Mfa device arn or serial number",
+    },
+    AccountField {
+        name: "mfa-secret",
+        value_name: "secret",
+        help: "Secret of virtual mfa device",
+    },
+    AccountField {
+        name: "external-id",
+        value_name: "external_id",
+        help: "sts:ExternalId required by the role's trust policy",
+    },
+    AccountField {
+        name: "duration-seconds",
+        value_name: "seconds",
+        help: "Session duration (900-43200)",
+    },
+    AccountField {
+        name: "role-session-name",
+        value_name: "template",
+        help: "Role session name template",
+    },
+    AccountField {
+        name: "source-identity",
+        value_name: "template",
+        help: "sts:SourceIdentity template",
+    },
+    AccountField {
+        name: "session-policy",
+        value_name: "policy",
+        help: "Inline session policy (JSON or file://path)",
+    },
+    AccountField {
+        name: "policy-arns",
+        value_name: "arns",
+        help: "Managed policy arns used as session policies (comma separated)",
+    },
+    AccountField {
+        name: "session-tags",
+        value_name: "tags",
+        help: "Session tags (key=value, comma separated)",
+    },
+    AccountField {
+        name: "transitive-tag-keys",
+        value_name: "keys",
+        help: "Session tag keys that persist through role chaining (comma separated)",
+    },
+    AccountField {
+        name: "sts-region",
+        value_name: "sts_region",
+        help: "Region of STS endpoint",
+    },
+    AccountField {
+        name: "sts-endpoint",
+        value_name: "sts_endpoint",
+        help: "Custom URL of STS endpoint",
+    },
+    AccountField {
+        name: "refresh-margin-seconds",
+        value_name: "seconds",
+        help: "Cached credentials are reused until this many seconds before expiry",
+    },
+    AccountField {
+        name: "credential-output",
+        value_name: "type",
+        help: "Output format of assume role result",
+    },
+    AccountField {
+        name: "output",
+        value_name: "output",
+        help: "Output type of awscli",
+    },
+    AccountField {
+        name: "region",
+        value_name: "region",
+        help: "Default region",
+    },
+];
+
+// Parsers of the values shared by the wizard and the command line.
+pub fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+pub fn parse_region(value: &str) -> Result<Region, String> {
+    match Region::from_str(value) {
+        Ok(region) => Ok(region),
+        Err(e) => Err(format!("parse region error: {}", e)),
+    }
+}
+
+pub fn parse_policy_arns(value: &str) -> Result<Vec<String>, String> {
+    let arns = split_list(value);
+    for arn in arns.iter() {
+        validate_policy_arn(arn)?;
+    }
+    Ok(arns)
+}
+
+pub fn parse_session_tags(value: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut tags = BTreeMap::new();
+    for text in value.split(',').filter(|t| !t.trim().is_empty()) {
+        let (key, value) = parse_session_tag(text)?;
+        tags.insert(key, value);
+    }
+    Ok(tags)
+}
+
+pub fn validate_transitive_tag_keys(
+    keys: &[String],
+    tags: Option<&BTreeMap<String, String>>,
+) -> Result<(), String> {
+    for key in keys.iter() {
+        if !tags.is_some_and(|tags| tags.contains_key(key)) {
+            return Err(format!("transitive tag key is not a session tag: {}", key));
+        }
+    }
+    Ok(())
+}
+
+// Values are parsed as they are entered in the wizard.
+pub fn set_account_field(account: &mut Account, name: &str, value: &str) -> Result<(), String> {
+    match name {
+        "source-account" => account.source_account = Some(value.to_string()),
+        "source-profile" => account.source_profile = Some(value.to_string()),
+        "role-arn" => {
+            parse_role_arn(value)?;
+            account.role_arn = value.to_string();
+        }
+        "mfa-arn" => {
+            validate_mfa_serial(value)?;
+            account.mfa_arn = Some(value.to_string());
+        }
+        "mfa-secret" => {
+            TOTP::new(&value.to_string())?;
+            account.mfa_secret = Some(value.to_string());
+        }
+        "external-id" => {
+            validate_external_id(value)?;
+            account.external_id = Some(value.to_string());
+        }
+        "duration-seconds" => account.duration_seconds = Some(parse_duration_seconds(value)?),
+        "role-session-name" => {
+            validate_role_session_name_template(value)?;
+            account.role_session_name = Some(value.to_string());
+        }
+        "source-identity" => {
+            validate_source_identity_template(value)?;
+            account.source_identity = Some(value.to_string());
+        }
+        "session-policy" => {
+            load_session_policy(value)?;
+            account.session_policy = Some(value.to_string());
+        }
+        "policy-arns" => account.policy_arns = Some(parse_policy_arns(value)?),
+        "session-tags" => account.session_tags = Some(parse_session_tags(value)?),
+        "transitive-tag-keys" => account.transitive_tag_keys = Some(split_list(value)),
+        "sts-region" => account.sts_region = Some(parse_region(value)?),
+        "sts-endpoint" => {
+            validate_sts_endpoint(value)?;
+            account.sts_endpoint = Some(value.to_string());
+        }
+        "refresh-margin-seconds" => {
+            account.refresh_margin_seconds = Some(parse_refresh_margin_seconds(value)?)
+        }
+        "credential-output" => account.credential_output = CredentialOutputTarget::from_str(value)?,
+        "output" => account.output = Some(AwsCliOutput::from_str(value)?),
+        "region" => account.region = Some(parse_region(value)?),
+        _ => return Err(format!("unknown field: {}", name)),
+    }
+    Ok(())
+}
+
+pub fn unset_account_field(account: &mut Account, name: &str) -> Result<(), String> {
+    match name {
+        "source-account" => account.source_account = None,
+        "source-profile" => account.source_profile = None,
+        "role-arn" | "credential-output" => {
+            return Err(format!("{} is required and cannot be unset", name))
+        }
+        "mfa-arn" => account.mfa_arn = None,
+        "mfa-secret" => {
+            account.mfa_secret = None;
+            account.mfa_secret_ref = None;
+        }
+        "external-id" => account.external_id = None,
+        "duration-seconds" => account.duration_seconds = None,
+        "role-session-name" => account.role_session_name = None,
+        "source-identity" => account.source_identity = None,
+        "session-policy" => account.session_policy = None,
+        "policy-arns" => account.policy_arns = None,
+        "session-tags" => account.session_tags = None,
+        "transitive-tag-keys" => account.transitive_tag_keys = None,
+        "sts-region" => account.sts_region = None,
+        "sts-endpoint" => account.sts_endpoint = None,
+        "refresh-margin-seconds" => account.refresh_margin_seconds = None,
+        "output" => account.output = None,
+        "region" => account.region = None,
+        _ => return Err(format!("unknown field: {}", name)),
+    }
+    Ok(())
+}
+
+// Checks the whole account, including the values which depend on other fields or accounts.
+pub fn validate_account(
+    config: &MasqueradeConfig,
+    name: &str,
+    account: &Account,
+) -> Result<(), String> {
+    if let Some(source_account) = &account.source_account {
+        if account.source_profile.is_some() {
+            return Err("source account and source profile cannot be used together".to_string());
+        }
+        if !config.accounts.contains_key(source_account) {
+            return Err(format!("account \"{}\" does not exist.", source_account));
+        }
+        let mut chained = MasqueradeConfig {
+            accounts: config.accounts.clone(),
+        };
+        chained.accounts.insert(name.to_string(), account.clone());
+        chained.resolve_chain(name)?;
+    }
+    parse_role_arn(&account.role_arn)?;
+    match &account.mfa_arn {
+        Some(mfa_arn) => validate_mfa_serial(mfa_arn)?,
+        None if account.mfa_secret.is_some() || account.mfa_secret_ref.is_some() => {
+            return Err("mfa secret requires mfa arn".to_string())
+        }
+        None => (),
+    }
+    if let Some(secret) = &account.mfa_secret {
+        TOTP::new(secret)?;
+    }
+    if let Some(reference) = &account.mfa_secret_ref {
+        parse_secret_ref(reference)?;
+    }
+    if let Some(external_id) = &account.external_id {
+        validate_external_id(external_id)?;
+    }
+    if let Some(duration) = account.duration_seconds {
+        validate_duration_seconds(duration)?;
+    }
+    if let Some(template) = &account.role_session_name {
+        validate_role_session_name_template(template)?;
+    }
+    if let Some(template) = &account.source_identity {
+        validate_source_identity_template(template)?;
+    }
+    if let Some(policy) = &account.session_policy {
+        load_session_policy(policy)?;
+    }
+    for arn in account.policy_arns.iter().flatten() {
+        validate_policy_arn(arn)?;
+    }
+    for (key, value) in account.session_tags.iter().flatten() {
+        validate_session_tag(key, value)?;
+    }
+    if let Some(keys) = &account.transitive_tag_keys {
+        validate_transitive_tag_keys(keys, account.session_tags.as_ref())?;
+    }
+    if let Some(endpoint) = &account.sts_endpoint {
+        validate_sts_endpoint(endpoint)?;
+    }
+    if let Some(margin) = account.refresh_margin_seconds {
+        validate_refresh_margin_seconds(margin)?;
+    }
+    Ok(())
+}
+
+// Fields of the JSON (same as config.json) overwrite those of the account.
+pub fn merge_account_json(account: &Account, text: &str) -> Result<Account, String> {
+    let input: serde_json::Value = match serde_json::from_str(text) {
+        Ok(input) => input,
+        Err(e) => return Err(format!("failed to parse JSON of account: {}", e)),
+    };
+    let fields = match input.as_object() {
+        Some(fields) => fields,
+        None => return Err("JSON of account must be an object".to_string()),
+    };
+    let mut merged = serde_json::to_value(account).unwrap();
+    let object = merged.as_object_mut().unwrap();
+    for (key, value) in fields.iter() {
+        if !object.contains_key(key) {
+            return Err(format!("unknown field of account: {}", key));
+        }
+        object.insert(key.clone(), value.clone());
+    }
+    match serde_json::from_value(merged) {
+        Ok(account) => Ok(account),
+        Err(e) => Err(format!("invalid JSON of account: {}", e)),
+    }
+}
+
+#[test]
+fn test_set_account_field() {
+    let mut account: Account = serde_json::from_str(
+        r#"{"roleArn": "", "credentialOutput": "SharedCredentials", "region": null}"#,
+    )
+    .unwrap();
+
+    set_account_field(
+        &mut account,
+        "role-arn",
+        "arn:aws:iam::000000000000:role/hestia",
+    )
+    .unwrap();
+    set_account_field(&mut account, "session-tags", "Project=x, Team=y").unwrap();
+    set_account_field(&mut account, "transitive-tag-keys", "Project").unwrap();
+    set_account_field(&mut account, "credential-output", "bash").unwrap();
+    set_account_field(&mut account, "region", "ap-northeast-1").unwrap();
+    assert_eq!(account.role_arn, "arn:aws:iam::000000000000:role/hestia");
+    assert_eq!(account.session_tags.as_ref().unwrap().len(), 2);
+    assert_eq!(account.region, Some(Region::ApNortheast1));
+
+    assert!(set_account_field(
+        &mut account,
+        "role-arn",
+        "arn:aws:iam::000000000000:user/bell"
+    )
+    .is_err());
+    assert!(set_account_field(&mut account, "duration-seconds", "60").is_err());
+    assert!(set_account_field(&mut account, "policy-arns", "ReadOnlyAccess").is_err());
+    assert!(set_account_field(&mut account, "session-tags", "Project").is_err());
+    assert!(set_account_field(&mut account, "output", "yaml").is_err());
+    assert!(set_account_field(&mut account, "name", "loki").is_err());
+
+    let config = MasqueradeConfig::new();
+    assert!(validate_account(&config, "hestia", &account).is_ok());
+    unset_account_field(&mut account, "region").unwrap();
+    assert_eq!(account.region, None);
+    assert!(unset_account_field(&mut account, "role-arn").is_err());
+    set_account_field(&mut account, "transitive-tag-keys", "Owner").unwrap();
+    assert!(validate_account(&config, "hestia", &account).is_err());
+}
+
+#[test]
+fn test_merge_account_json() {
+    let account: Account = serde_json::from_str(
+        r#"{"roleArn": "arn:aws:iam::000000000000:role/hestia", "credentialOutput": "bash", "region": "ap-northeast-1"}"#,
+    )
+    .unwrap();
+
+    let merged = merge_account_json(
+        &account,
+        r#"{"durationSeconds": 7200, "policyArns": ["arn:aws:iam::aws:policy/ReadOnlyAccess"]}"#,
+    )
+    .unwrap();
+    assert_eq!(merged.role_arn, account.role_arn);
+    assert_eq!(merged.duration_seconds, Some(7200));
+    assert_eq!(merged.region, Some(Region::ApNortheast1));
+
+    assert!(merge_account_json(&account, r#"{"rolearn": "x"}"#).is_err());
+    assert!(merge_account_json(&account, r#"{"durationSeconds": "x"}"#).is_err());
+    assert!(merge_account_json(&account, "[]").is_err());
+}
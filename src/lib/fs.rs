@@ -1,8 +1,16 @@
+use crate::lib::cache::parse_expiration;
 use crate::lib::dirs::MASQUERADE_PATH;
+use crate::lib::ini::IniDocument;
+use crate::lib::permission::{create_private_dir_all, create_private_file};
+use chrono::{DateTime, Utc};
+use fs2::FileExt;
 use rusoto_core::Region;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
 use std::{
     collections::{BTreeMap, HashMap},
     str,
@@ -16,6 +24,7 @@ pub enum CredentialOutputTarget {
     Fish,
     PowerShell,
     SharedCredentials,
+    CredentialProcess,
 }
 
 impl CredentialOutputTarget {
@@ -25,6 +34,7 @@ impl CredentialOutputTarget {
             CredentialOutputTarget::Fish => "fish",
             CredentialOutputTarget::PowerShell => "PowerShell",
             CredentialOutputTarget::SharedCredentials => "SharedCredentials",
+            CredentialOutputTarget::CredentialProcess => "CredentialProcess",
         }
     }
 
@@ -34,6 +44,7 @@ impl CredentialOutputTarget {
             "fish" => Ok(CredentialOutputTarget::Fish),
             "PowerShell" => Ok(CredentialOutputTarget::PowerShell),
             "SharedCredentials" => Ok(CredentialOutputTarget::SharedCredentials),
+            "CredentialProcess" => Ok(CredentialOutputTarget::CredentialProcess),
             _ => Err("Invalid Name of CredentialOutputTarget".to_string()),
         }
     }
@@ -65,6 +76,15 @@ impl AwsCliOutput {
     pub fn to_string(&self) -> String {
         self.to_str().to_string()
     }
+
+    pub fn from_str(v: &str) -> Result<AwsCliOutput, String> {
+        match v {
+            "json" => Ok(AwsCliOutput::Json),
+            "text" => Ok(AwsCliOutput::Text),
+            "table" => Ok(AwsCliOutput::Table),
+            _ => Err("Invalid Name of AwsCliOutput".to_string()),
+        }
+    }
 }
 
 impl Display for AwsCliOutput {
@@ -78,9 +98,23 @@ impl Display for AwsCliOutput {
 pub struct Account {
     // assume setting
     pub source_profile: Option<String>,
+    pub source_account: Option<String>,
     pub role_arn: String,
     pub mfa_arn: Option<String>,
     pub mfa_secret: Option<String>,
+    pub mfa_secret_ref: Option<String>,
+    pub external_id: Option<String>,
+    pub duration_seconds: Option<i64>,
+    pub role_session_name: Option<String>,
+    pub source_identity: Option<String>,
+    pub session_policy: Option<String>,
+    pub policy_arns: Option<Vec<String>>,
+    pub session_tags: Option<BTreeMap<String, String>>,
+    pub transitive_tag_keys: Option<Vec<String>>,
+    #[serde(default, with = "ext_region")]
+    pub sts_region: Option<Region>,
+    pub sts_endpoint: Option<String>,
+    pub refresh_margin_seconds: Option<i64>,
     // output setting
     pub credential_output: CredentialOutputTarget,
     pub output: Option<AwsCliOutput>,
@@ -88,7 +122,30 @@ pub struct Account {
     pub region: Option<Region>,
 }
 
+// Shown instead of secrets unless they are requested explicitly.
+pub const REDACTED_SECRET: &str = "********";
+
 impl Account {
+    pub fn redacted(&self) -> Account {
+        let mut account = self.clone();
+        if account.mfa_secret.is_some() {
+            account.mfa_secret = Some(REDACTED_SECRET.to_string());
+        }
+        account
+    }
+
+    // The form of the account shown by every command: {"name": {...}}.
+    pub fn to_display_string(&self, name: &str, show_secrets: bool) -> String {
+        let account = if show_secrets {
+            self.clone()
+        } else {
+            self.redacted()
+        };
+        let mut map: HashMap<&str, Account> = HashMap::new();
+        map.insert(name, account);
+        serde_json::to_string_pretty(&map).unwrap()
+    }
+
     pub fn create_shared_config(&self) -> Option<HashMap<String, String>> {
         let mut map: HashMap<String, String> = HashMap::new();
 
@@ -106,6 +163,79 @@ impl Account {
             None
         }
     }
+
+    pub fn create_credential_process_config(
+        &self,
+        name: &str,
+        program: &str,
+    ) -> HashMap<String, String> {
+        let mut map = self.create_shared_config().unwrap_or_default();
+        map.insert(
+            "credential_process".to_string(),
+            format!(
+                "{} assume -a {} -c {}",
+                quote_argument(program),
+                quote_argument(name),
+                CredentialOutputTarget::CredentialProcess
+            ),
+        );
+        map.insert(GENERATED_PROFILE_MARKER_KEY.to_string(), name.to_string());
+
+        map
+    }
+}
+
+const PROGRAM_NAME: &str = "aws-masquerade";
+pub const GENERATED_PROFILE_MARKER_KEY: &str = "x_masquerade_account";
+
+// The AWS CLI and SDKs don't always run credential_process with the PATH of the shell.
+pub fn current_program_path() -> String {
+    match std::env::current_exe() {
+        Ok(path) => path.to_string_lossy().to_string(),
+        Err(_) => PROGRAM_NAME.to_string(),
+    }
+}
+
+// credential_process is split like a shell command line.
+fn quote_argument(arg: &str) -> String {
+    let is_plain = arg
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "_-.@+=,:/".contains(c));
+    if is_plain && !arg.is_empty() {
+        arg.to_string()
+    } else {
+        format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+pub const MIN_DURATION_SECONDS: i64 = 900;
+pub const MAX_DURATION_SECONDS: i64 = 43200;
+
+pub fn validate_duration_seconds(duration: i64) -> Result<i64, String> {
+    if (MIN_DURATION_SECONDS..=MAX_DURATION_SECONDS).contains(&duration) {
+        Ok(duration)
+    } else {
+        Err(format!(
+            "duration seconds must be between {} and {}: {}",
+            MIN_DURATION_SECONDS, MAX_DURATION_SECONDS, duration
+        ))
+    }
+}
+
+pub fn parse_duration_seconds(text: &str) -> Result<i64, String> {
+    match text.parse::<i64>() {
+        Ok(duration) => validate_duration_seconds(duration),
+        Err(e) => Err(format!("failed to parse duration seconds: {}", e)),
+    }
+}
+
+#[test]
+fn test_parse_duration_seconds() {
+    assert_eq!(parse_duration_seconds("900"), Ok(900));
+    assert_eq!(parse_duration_seconds("43200"), Ok(43200));
+    assert!(parse_duration_seconds("899").is_err());
+    assert!(parse_duration_seconds("43201").is_err());
+    assert!(parse_duration_seconds("1h").is_err());
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -119,6 +249,216 @@ impl MasqueradeConfig {
             accounts: BTreeMap::new(),
         }
     }
+
+    // Returns the accounts to assume in order, starting from the one which uses the source profile.
+    pub fn resolve_chain<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> Result<Vec<(&'a str, &'a Account)>, String> {
+        let mut chain: Vec<(&str, &Account)> = Vec::new();
+        let mut current = name;
+        loop {
+            if chain.iter().any(|(n, _)| *n == current) {
+                let mut names: Vec<&str> = chain.iter().map(|(n, _)| *n).collect();
+                names.push(current);
+                return Err(format!("circular source account: {}", names.join(" -> ")));
+            }
+            let account = match self.accounts.get(current) {
+                Some(account) => account,
+                None => return Err(format!("Account \"{}\" does not exist.", current)),
+            };
+            chain.push((current, account));
+            match &account.source_account {
+                Some(source) => current = source,
+                None => break,
+            }
+        }
+        chain.reverse();
+        Ok(chain)
+    }
+}
+
+#[test]
+fn test_resolve_chain() {
+    let text = r#"{"accounts": {
+        "guild": {"sourceProfile": "default", "roleArn": "arn:aws:iam::000000000000:role/guild", "credentialOutput": "bash", "region": null},
+        "hestia": {"sourceAccount": "guild", "roleArn": "arn:aws:iam::111111111111:role/hestia", "credentialOutput": "bash", "region": null},
+        "bell": {"sourceAccount": "hestia", "roleArn": "arn:aws:iam::222222222222:role/bell", "credentialOutput": "bash", "region": null},
+        "loki": {"sourceAccount": "freya", "roleArn": "arn:aws:iam::333333333333:role/loki", "credentialOutput": "bash", "region": null},
+        "freya": {"sourceAccount": "loki", "roleArn": "arn:aws:iam::444444444444:role/freya", "credentialOutput": "bash", "region": null},
+        "ouranos": {"sourceAccount": "nobody", "roleArn": "arn:aws:iam::555555555555:role/ouranos", "credentialOutput": "bash", "region": null}
+    }}"#;
+    let config: MasqueradeConfig = serde_json::from_str(text).unwrap();
+
+    let names: Vec<&str> = config
+        .resolve_chain("bell")
+        .unwrap()
+        .iter()
+        .map(|(n, _)| *n)
+        .collect();
+    assert_eq!(names, vec!["guild", "hestia", "bell"]);
+
+    assert_eq!(
+        config.resolve_chain("loki").unwrap_err(),
+        "circular source account: loki -> freya -> loki"
+    );
+    assert!(config.resolve_chain("ouranos").is_err());
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncedProfiles {
+    pub synced: Vec<String>,
+    pub removed: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+// Profiles generated before have the marker key, so those of removed accounts can be deleted.
+// Profiles of the same name without the marker are written by hand and skipped unless forced.
+pub fn sync_credential_process_profiles(
+    shared_config: &mut IniDocument,
+    config: &MasqueradeConfig,
+    program: &str,
+    force: bool,
+) -> SyncedProfiles {
+    let mut result = SyncedProfiles::default();
+    for section in shared_config.sections() {
+        if let Some(name) = shared_config.get(&section, GENERATED_PROFILE_MARKER_KEY) {
+            if !config.accounts.contains_key(&name) {
+                shared_config.remove_section(&section);
+                result.removed.push(name);
+            }
+        }
+    }
+    result.removed.sort();
+
+    for (name, account) in config.accounts.iter() {
+        let profile = format!("profile {}", name);
+        if !force
+            && shared_config.has_section(&profile)
+            && shared_config
+                .get(&profile, GENERATED_PROFILE_MARKER_KEY)
+                .is_none()
+        {
+            result.skipped.push(name.clone());
+            continue;
+        }
+        shared_config.set_section(
+            &profile,
+            &account.create_credential_process_config(name, program),
+        );
+        result.synced.push(name.clone());
+    }
+
+    result
+}
+
+#[test]
+fn test_sync_credential_process_profiles() {
+    let text = r#"{"accounts": {
+        "hestia": {"roleArn": "arn:aws:iam::000000000000:role/hestia", "credentialOutput": "bash", "output": "json", "region": "ap-northeast-1"},
+        "bell cranel": {"roleArn": "arn:aws:iam::111111111111:role/bell", "credentialOutput": "bash", "region": null}
+    }}"#;
+    let config: MasqueradeConfig = serde_json::from_str(text).unwrap();
+    let mut shared_config = IniDocument::parse(
+        "[default]
+region = us-east-1
+
+[profile hestia]
+region = us-west-2
+cli_pager =
+
+[profile loki]
+x_masquerade_account = loki
+",
+    );
+    let program = "/opt/aws masquerade/bin/aws-masquerade";
+
+    let result = sync_credential_process_profiles(&mut shared_config, &config, program, false);
+
+    assert_eq!(result.removed, vec!["loki".to_string()]);
+    assert_eq!(result.skipped, vec!["hestia".to_string()]);
+    assert_eq!(result.synced, vec!["bell cranel".to_string()]);
+    let expected = r#"[default]
+region = us-east-1
+
+[profile hestia]
+region = us-west-2
+cli_pager =
+
+[profile bell cranel]
+credential_process = "/opt/aws masquerade/bin/aws-masquerade" assume -a "bell cranel" -c CredentialProcess
+x_masquerade_account = bell cranel
+"#;
+    assert_eq!(shared_config.to_string(), expected);
+
+    let result = sync_credential_process_profiles(&mut shared_config, &config, program, true);
+
+    assert!(result.skipped.is_empty());
+    assert_eq!(
+        shared_config.get("profile hestia", "credential_process"),
+        Some(format!(
+            "\"{}\" assume -a hestia -c CredentialProcess",
+            program
+        ))
+    );
+    assert_eq!(
+        shared_config.get("profile hestia", "cli_pager"),
+        Some("".to_string())
+    );
+}
+
+#[test]
+fn test_update_shared_config_profile() {
+    let text = r#"{"accounts": {
+        "hestia": {"roleArn": "arn:aws:iam::000000000000:role/hestia", "credentialOutput": "SharedCredentials", "output": "json", "region": "ap-northeast-1"}
+    }}"#;
+    let mut config: MasqueradeConfig = serde_json::from_str(text).unwrap();
+    let mut shared_config = IniDocument::parse("[profile hestia]\ncli_pager =\n");
+    let program = "aws-masquerade";
+    sync_credential_process_profiles(&mut shared_config, &config, program, true);
+
+    let account = config.accounts.get_mut("hestia").unwrap();
+    account.region = Some(Region::UsWest2);
+    assert!(update_shared_config_profile(
+        &mut shared_config,
+        "hestia",
+        account
+    ));
+
+    let expected = account.create_credential_process_config("hestia", program);
+    for (key, value) in expected.iter() {
+        assert_eq!(
+            shared_config.get("profile hestia", key).as_ref(),
+            Some(value)
+        );
+    }
+    assert_eq!(
+        shared_config.get("profile hestia", "cli_pager"),
+        Some("".to_string())
+    );
+
+    account.region = None;
+    account.output = None;
+    assert!(update_shared_config_profile(
+        &mut shared_config,
+        "hestia",
+        account
+    ));
+    assert_eq!(
+        shared_config.to_string(),
+        "[profile hestia]\ncli_pager =\ncredential_process = aws-masquerade assume -a hestia -c CredentialProcess\nx_masquerade_account = hestia\n"
+    );
+    assert!(!update_shared_config_profile(
+        &mut shared_config,
+        "hestia",
+        account
+    ));
+    assert!(!update_shared_config_profile(
+        &mut shared_config,
+        "loki",
+        account
+    ));
+    assert!(!shared_config.has_section("profile loki"));
 }
 
 mod ext_region {
@@ -156,6 +496,87 @@ mod ext_region {
     }
 }
 
+// The text is written into a temporary file next to the destination and renamed over it,
+// so that a concurrent reader never sees a partially written file.
+fn write_atomically(path: &Path, text: &str, private: bool) -> std::io::Result<()> {
+    // Renaming over a symlink would replace the link itself (e.g. ~/.aws/credentials in a dotfiles repo).
+    let resolved = std::fs::canonicalize(path);
+    let path = match &resolved {
+        Ok(resolved) => resolved.as_path(),
+        Err(_) => path,
+    };
+    let file_name = match path.file_name() {
+        Some(name) => name.to_string_lossy(),
+        None => return Err(std::io::Error::new(ErrorKind::InvalidInput, "no file name")),
+    };
+    let temp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+    let result = (|| {
+        let mut file = if private {
+            create_private_file(&temp_path)?
+        } else {
+            let file = File::create(&temp_path)?;
+            if let Ok(metadata) = std::fs::metadata(path) {
+                file.set_permissions(metadata.permissions())?;
+            }
+            file
+        };
+        file.write_all(text.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&temp_path, path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+pub fn write_file_atomically(path: &Path, text: &str) -> std::io::Result<()> {
+    write_atomically(path, text, false)
+}
+
+pub fn write_private_file_atomically(path: &Path, text: &str) -> std::io::Result<()> {
+    write_atomically(path, text, true)
+}
+
+// Advisory lock held across a read-modify-write of the file, released when the returned file is dropped.
+fn lock_file(path: &Path) -> Result<File, String> {
+    let file_name = match path.file_name() {
+        Some(name) => name.to_string_lossy(),
+        None => return Err(format!("failed to resolve lock file of {}", path.display())),
+    };
+    let lock_path = path.with_file_name(format!(".{}.lock", file_name));
+    if let Some(dir) = lock_path.parent() {
+        if let Err(e) = create_private_dir_all(dir) {
+            return Err(format!("failed to create directories of lock file: {}", e));
+        }
+    }
+    let file = match OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+    {
+        Ok(file) => file,
+        Err(e) => return Err(format!("failed to open lock file: {}", e)),
+    };
+    match file.lock_exclusive() {
+        Ok(_) => Ok(file),
+        Err(e) => Err(format!("failed to lock {}: {}", path.display(), e)),
+    }
+}
+
+pub fn lock_shared_config() -> Result<File, String> {
+    lock_file(MASQUERADE_PATH.shared_config())
+}
+
+pub fn lock_shared_credentials() -> Result<File, String> {
+    lock_file(MASQUERADE_PATH.shared_credentials())
+}
+
+pub fn lock_cache() -> Result<File, String> {
+    lock_file(MASQUERADE_PATH.cache())
+}
+
 pub fn load_config() -> Result<MasqueradeConfig, String> {
     let config_path = MASQUERADE_PATH.config();
     let text = match std::fs::read_to_string(config_path) {
@@ -171,7 +592,7 @@ pub fn load_config() -> Result<MasqueradeConfig, String> {
 pub fn save_config(config: &MasqueradeConfig) -> Result<(), String> {
     let config_path = MASQUERADE_PATH.config();
     let dir = config_path.parent().unwrap();
-    match std::fs::create_dir_all(dir) {
+    match create_private_dir_all(dir) {
         Ok(_) => (),
         Err(e) => return Err(format!("failed to create config directory: {}", e)),
     };
@@ -179,31 +600,36 @@ pub fn save_config(config: &MasqueradeConfig) -> Result<(), String> {
         Ok(text) => text,
         Err(e) => return Err(format!("failed to serialize config: {}", e)),
     };
-    match std::fs::write(config_path, text) {
+    match write_private_file_atomically(config_path, &text) {
         Ok(_) => Ok(()),
         Err(e) => Err(format!("failed to write config: {}", e)),
     }
 }
 
-pub fn load_shared_config() -> Result<HashMap<String, HashMap<String, String>>, String> {
+// A missing file is the same as an empty one, as the AWS CLI treats it.
+fn read_ini_file(path: &Path) -> std::io::Result<IniDocument> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => Ok(IniDocument::parse(&text)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(IniDocument::default()),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn load_shared_config() -> Result<IniDocument, String> {
     let path_shared_config = MASQUERADE_PATH.shared_config();
-    let text = match std::fs::read_to_string(path_shared_config) {
-        Ok(text) => text,
-        Err(e) => return Err(format!("failed to read shared config: {}", e)),
-    };
-    match serde_ini::from_str(&text) {
+    match read_ini_file(path_shared_config) {
         Ok(config) => Ok(config),
-        Err(e) => Err(format!("failed to parse shared config: {}", e)),
+        Err(e) => Err(format!("failed to read shared config: {}", e)),
     }
 }
 
-pub fn save_shared_config(config: &HashMap<String, HashMap<String, String>>) -> Result<(), String> {
+pub fn save_shared_config(config: &IniDocument) -> Result<(), String> {
     let path_shared_config = MASQUERADE_PATH.shared_config();
     let path_dir = match path_shared_config.parent() {
         Some(path) => path,
         None => return Err("failed to resolve directory of shared config".to_string()),
     };
-    match std::fs::create_dir_all(path_dir) {
+    match create_private_dir_all(path_dir) {
         Ok(_) => (),
         Err(e) => {
             return Err(format!(
@@ -213,49 +639,62 @@ pub fn save_shared_config(config: &HashMap<String, HashMap<String, String>>) ->
         }
     };
 
-    let text = match serde_ini::to_string(config) {
-        Ok(text) => text,
-        Err(e) => return Err(format!("failed to serialize shared config: {}", e)),
-    };
-    match std::fs::write(path_shared_config, text) {
+    match write_file_atomically(path_shared_config, &config.to_string()) {
         Ok(_) => Ok(()),
         Err(e) => Err(format!("failed to write shared config: {}", e)),
     }
 }
 
-pub fn add_shared_config<T>(name: T, data: &HashMap<String, String>) -> Result<(), String>
-where
-    T: Display,
-{
-    let mut config = load_shared_config()?;
+// Keys of a profile which are written from the settings of the account.
+const SHARED_CONFIG_KEYS: [&str; 2] = ["region", "output"];
+
+// Only the keys written from the account are updated, so the other keys of the profile
+// (e.g. credential_process of sync-profiles or hand-added ones) are kept.
+pub fn update_shared_config_profile(
+    shared_config: &mut IniDocument,
+    name: &str,
+    account: &Account,
+) -> bool {
     let profile_name = format!("profile {}", name);
+    let data = account.create_shared_config().unwrap_or_default();
+    let mut is_changed = false;
+    for key in SHARED_CONFIG_KEYS.iter() {
+        if !data.contains_key(*key) && shared_config.remove(&profile_name, key) {
+            is_changed = true;
+        }
+    }
+    if !data.is_empty() {
+        shared_config.set_section(&profile_name, &data);
+        is_changed = true;
+    }
+    is_changed
+}
 
-    config.insert(profile_name, data.clone());
+pub fn add_shared_config(name: &str, account: &Account) -> Result<(), String> {
+    let _lock = lock_shared_config()?;
+    let mut config = load_shared_config()?;
 
-    save_shared_config(&config)
+    if update_shared_config_profile(&mut config, name, account) {
+        save_shared_config(&config)?;
+    }
+    Ok(())
 }
 
-pub fn load_shared_credentials() -> Result<HashMap<String, HashMap<String, String>>, String> {
+pub fn load_shared_credentials() -> Result<IniDocument, String> {
     let path_shred_credentials = MASQUERADE_PATH.shared_credentials();
-    let text = match std::fs::read_to_string(path_shred_credentials) {
-        Ok(text) => text,
-        Err(e) => return Err(format!("failed to read shared credentials: {}", e)),
-    };
-    match serde_ini::from_str(&text) {
+    match read_ini_file(path_shred_credentials) {
         Ok(credentials) => Ok(credentials),
-        Err(e) => Err(format!("failed to deserialize shared credentials: {}", e)),
+        Err(e) => Err(format!("failed to read shared credentials: {}", e)),
     }
 }
 
-pub fn save_shared_credentials(
-    credentials: &HashMap<String, HashMap<String, String>>,
-) -> Result<(), String> {
+pub fn save_shared_credentials(credentials: &IniDocument) -> Result<(), String> {
     let path_shared_credentials = MASQUERADE_PATH.shared_credentials();
     let path_dir = match path_shared_credentials.parent() {
         Some(path) => path,
         None => return Err("failed to resolve directory of shared credentials".to_string()),
     };
-    match std::fs::create_dir_all(path_dir) {
+    match create_private_dir_all(path_dir) {
         Ok(_) => (),
         Err(e) => {
             return Err(format!(
@@ -265,11 +704,7 @@ pub fn save_shared_credentials(
         }
     };
 
-    let text = match serde_ini::to_string(credentials) {
-        Ok(text) => text,
-        Err(e) => return Err(format!("failed to serialize shared credentials: {}", e)),
-    };
-    match std::fs::write(path_shared_credentials, text) {
+    match write_private_file_atomically(path_shared_credentials, &credentials.to_string()) {
         Ok(_) => Ok(()),
         Err(e) => Err(format!("failed to write shared credentials: {}", e)),
     }
@@ -279,9 +714,233 @@ pub fn add_into_shared_credentials(
     name: &String,
     data: &HashMap<String, String>,
 ) -> Result<(), String> {
+    let _lock = lock_shared_credentials()?;
     let mut credentials = load_shared_credentials()?;
 
-    credentials.insert(name.clone(), data.clone());
+    credentials.replace_section(name, data);
 
     save_shared_credentials(&credentials)
 }
+
+// Keys which aws-masquerade writes into the shared credentials besides the credentials.
+pub const SECURITY_TOKEN_EXPIRES_KEY: &str = "x_security_token_expires";
+pub const PRINCIPAL_ARN_KEY: &str = "x_principal_arn";
+
+// A profile is generated by sync-profiles if it has the marker of the account, or by the
+// SharedCredentials output if it has just the keys which add_shared_config writes for the account.
+pub fn is_generated_profile(shared_config: &IniDocument, name: &str, account: &Account) -> bool {
+    let profile = format!("profile {}", name);
+    if shared_config
+        .get(&profile, GENERATED_PROFILE_MARKER_KEY)
+        .as_deref()
+        == Some(name)
+    {
+        return true;
+    }
+    match (
+        shared_config.section(&profile),
+        account.create_shared_config(),
+    ) {
+        (Some(entries), Some(expected)) => {
+            entries.len() == expected.len()
+                && entries
+                    .iter()
+                    .all(|(key, value)| expected.get(key) == Some(value))
+        }
+        _ => false,
+    }
+}
+
+pub fn is_generated_credentials(credentials: &IniDocument, name: &str) -> bool {
+    credentials.get(name, SECURITY_TOKEN_EXPIRES_KEY).is_some()
+}
+
+// Only the sections with the expiry key are written by aws-masquerade.
+// Returns their names and expirations (None if it cannot be parsed).
+pub fn find_masquerade_credentials(
+    credentials: &IniDocument,
+) -> Vec<(String, Option<DateTime<Utc>>)> {
+    credentials
+        .sections()
+        .into_iter()
+        .filter_map(|section| {
+            let expires = credentials.get(&section, SECURITY_TOKEN_EXPIRES_KEY)?;
+            Some((section, parse_expiration(&expires)))
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct CleanedCredentials {
+    // sections to remove and the reasons
+    pub removed: Vec<(String, String)>,
+    // sections kept because their expiration cannot be parsed
+    pub invalid: Vec<String>,
+}
+
+// Expired credentials written by aws-masquerade are removed, or all of them if all is set.
+// The other sections are never touched.
+pub fn find_credentials_to_clean(
+    credentials: &IniDocument,
+    now: DateTime<Utc>,
+    all: bool,
+) -> CleanedCredentials {
+    let mut result = CleanedCredentials::default();
+    for (section, expiration) in find_masquerade_credentials(credentials) {
+        let status = match expiration {
+            Some(expiration) if expiration <= now => format!("expired at {}", expiration),
+            Some(expiration) if all => format!("expires at {}", expiration),
+            Some(_) => continue,
+            None if all => "unknown expiration".to_string(),
+            None => {
+                result.invalid.push(section);
+                continue;
+            }
+        };
+        result.removed.push((section, status));
+    }
+    result
+}
+
+#[test]
+fn test_find_masquerade_credentials() {
+    let credentials = IniDocument::parse(
+        "[default]
+aws_access_key_id = AKIA0000000000000000
+
+[hestia]
+aws_access_key_id = ASIA0000000000000000
+x_security_token_expires = 2020-01-01T00:00:00Z
+
+[bell]
+x_security_token_expires = someday
+",
+    );
+    let found = find_masquerade_credentials(&credentials);
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].0, "hestia");
+    assert_eq!(found[0].1, parse_expiration("2020-01-01T00:00:00Z"));
+    assert_eq!(found[1], ("bell".to_string(), None));
+}
+
+#[test]
+fn test_find_credentials_to_clean() {
+    let credentials = IniDocument::parse(
+        "[default]
+aws_access_key_id = AKIA0000000000000000
+
+[hestia]
+x_security_token_expires = 2020-01-01T00:00:00Z
+
+[loki]
+x_security_token_expires = 2020-01-01T02:00:00Z
+
+[bell]
+x_security_token_expires = someday
+",
+    );
+    let now = parse_expiration("2020-01-01T01:00:00Z").unwrap();
+
+    let result = find_credentials_to_clean(&credentials, now, false);
+    assert_eq!(
+        result.removed,
+        vec![(
+            "hestia".to_string(),
+            "expired at 2020-01-01 00:00:00 UTC".to_string()
+        )]
+    );
+    assert_eq!(result.invalid, vec!["bell".to_string()]);
+
+    let result = find_credentials_to_clean(&credentials, now, true);
+    let removed: Vec<&str> = result.removed.iter().map(|(s, _)| s.as_str()).collect();
+    assert_eq!(removed, vec!["hestia", "loki", "bell"]);
+    assert_eq!(
+        result.removed[1].1,
+        "expires at 2020-01-01 02:00:00 UTC".to_string()
+    );
+    assert_eq!(result.removed[2].1, "unknown expiration".to_string());
+    assert!(result.invalid.is_empty());
+}
+
+#[test]
+fn test_is_generated_profile() {
+    let text = r#"{"roleArn": "arn:aws:iam::000000000000:role/hestia", "credentialOutput": "SharedCredentials", "output": "json", "region": "ap-northeast-1"}"#;
+    let account: Account = serde_json::from_str(text).unwrap();
+    let shared_config = IniDocument::parse(
+        "[profile hestia]
+output = json
+region = ap-northeast-1
+
+[profile bell]
+region = ap-northeast-1
+output = json
+source_profile = default
+
+[profile loki]
+credential_process = aws-masquerade assume -a loki -c CredentialProcess
+x_masquerade_account = loki
+",
+    );
+    assert!(is_generated_profile(&shared_config, "hestia", &account));
+    assert!(!is_generated_profile(&shared_config, "bell", &account));
+    assert!(is_generated_profile(&shared_config, "loki", &account));
+    assert!(!is_generated_profile(&shared_config, "freya", &account));
+
+    let mut account = account;
+    account.region = None;
+    assert!(!is_generated_profile(&shared_config, "hestia", &account));
+}
+
+#[test]
+fn test_write_file_atomically() {
+    let dir = std::env::temp_dir().join(format!("aws-masquerade-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("credentials");
+
+    write_file_atomically(&path, "[hestia]\n").unwrap();
+    write_file_atomically(&path, "[bell]\n").unwrap();
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "[bell]\n");
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_write_file_atomically_through_symlink() {
+    let dir = std::env::temp_dir().join(format!("aws-masquerade-link-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let target = dir.join("dotfiles-credentials");
+    let link = dir.join("credentials");
+    std::fs::write(&target, "[hestia]\n").unwrap();
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    write_file_atomically(&link, "[bell]\n").unwrap();
+
+    assert!(std::fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "[bell]\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_account_to_display_string() {
+    let text = r#"{"roleArn": "arn:aws:iam::000000000000:role/hestia", "mfaArn": "arn:aws:iam::000000000000:mfa/bell", "mfaSecret": "JBSWY3DPEHPK3PXP", "credentialOutput": "bash", "region": null}"#;
+    let account: Account = serde_json::from_str(text).unwrap();
+
+    let redacted = account.to_display_string("hestia", false);
+    assert!(!redacted.contains("JBSWY3DPEHPK3PXP"));
+    assert!(redacted.contains(&format!("\"mfaSecret\": \"{}\"", REDACTED_SECRET)));
+    assert!(redacted.contains("arn:aws:iam::000000000000:mfa/bell"));
+    assert!(account
+        .to_display_string("hestia", true)
+        .contains("\"mfaSecret\": \"JBSWY3DPEHPK3PXP\""));
+
+    let mut account = account;
+    account.mfa_secret = None;
+    assert!(account
+        .to_display_string("hestia", false)
+        .contains("\"mfaSecret\": null"));
+}
@@ -0,0 +1,402 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+// Editor of the shared config and credentials files which keeps every line it doesn't need to touch
+// (comments, ordering, spacing and unknown syntax) byte-for-byte.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct IniDocument {
+    lines: Vec<String>,
+    trailing_newline: bool,
+    crlf: bool,
+}
+
+#[derive(Debug, PartialEq)]
+enum Line<'a> {
+    Section(&'a str),
+    Entry(&'a str, &'a str),
+    // Indented lines continue the value of the previous entry (e.g. nested s3 settings).
+    Continuation,
+    Other,
+}
+
+fn parse_line(raw: &str) -> Line<'_> {
+    let line = raw.trim_end_matches('\r');
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+        return Line::Other;
+    }
+    if line.starts_with(char::is_whitespace) {
+        return Line::Continuation;
+    }
+    if trimmed.starts_with('[') {
+        return match trimmed.find(']') {
+            Some(index) => Line::Section(trimmed[1..index].trim()),
+            None => Line::Other,
+        };
+    }
+    match trimmed.find(['=', ':']) {
+        Some(index) if !trimmed[..index].trim().is_empty() => {
+            Line::Entry(trimmed[..index].trim(), trimmed[index + 1..].trim())
+        }
+        _ => Line::Other,
+    }
+}
+
+impl IniDocument {
+    pub fn parse(text: &str) -> IniDocument {
+        let mut lines: Vec<String> = text.split('\n').map(|l| l.to_string()).collect();
+        let trailing_newline = text.ends_with('\n');
+        if trailing_newline || text.is_empty() {
+            lines.pop();
+        }
+        IniDocument {
+            lines,
+            trailing_newline,
+            crlf: text.contains("\r\n"),
+        }
+    }
+
+    pub fn sections(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for raw in self.lines.iter() {
+            if let Line::Section(name) = parse_line(raw) {
+                if !names.iter().any(|n| n == name) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names
+    }
+
+    pub fn section(&self, name: &str) -> Option<Vec<(String, String)>> {
+        let (start, end) = self.find_section(name)?;
+        let entries = self.lines[start + 1..end]
+            .iter()
+            .filter_map(|raw| match parse_line(raw) {
+                Line::Entry(key, value) => Some((key.to_string(), value.to_string())),
+                _ => None,
+            })
+            .collect();
+        Some(entries)
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<String> {
+        let index = self.find_entry(section, key)?;
+        match parse_line(&self.lines[index]) {
+            Line::Entry(_, value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, section: &str, key: &str, value: &str) {
+        let line = format!("{} = {}", key, value);
+        if let Some(index) = self.find_entry(section, key) {
+            let end = self.continuation_end(index);
+            let line = self.with_line_ending(line, index);
+            self.lines.splice(index..end, vec![line]);
+            return;
+        }
+        match self.find_section(section) {
+            Some((start, end)) => {
+                // After the last entry, so the blank lines and comments of the next section stay with it.
+                let index = (start + 1..end)
+                    .rev()
+                    .find(|i| parse_line(&self.lines[*i]) != Line::Other)
+                    .map_or(start + 1, |i| i + 1);
+                if index == self.lines.len() {
+                    self.push_line(line);
+                } else {
+                    let line = self.with_line_ending(line, start);
+                    self.lines.insert(index, line);
+                }
+            }
+            None => {
+                if let Some(last) = self.lines.last() {
+                    if !last.trim().is_empty() {
+                        self.push_line(String::new());
+                    }
+                }
+                self.push_line(format!("[{}]", section));
+                self.push_line(line);
+            }
+        }
+    }
+
+    // Sets the given keys and keeps the other keys of the section.
+    pub fn set_section(&mut self, section: &str, entries: &HashMap<String, String>) {
+        let mut keys: Vec<&String> = entries.keys().collect();
+        keys.sort();
+        for key in keys {
+            self.set(section, key, &entries[key]);
+        }
+    }
+
+    pub fn remove(&mut self, section: &str, key: &str) -> bool {
+        match self.find_entry(section, key) {
+            Some(index) => {
+                let end = self.continuation_end(index);
+                self.lines.drain(index..end);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Replaces the keys of the section with the given ones, keeping its position and comments.
+    pub fn replace_section(&mut self, section: &str, entries: &HashMap<String, String>) {
+        if let Some(old_entries) = self.section(section) {
+            for (key, _) in old_entries {
+                if !entries.contains_key(&key) {
+                    self.remove(section, &key);
+                }
+            }
+        }
+        self.set_section(section, entries);
+    }
+
+    pub fn remove_section(&mut self, name: &str) -> bool {
+        let (start, mut end) = match self.find_section(name) {
+            Some(range) => range,
+            None => return false,
+        };
+        // Blank lines and comments just above the next section header belong to that section.
+        while end > start + 1 && end < self.lines.len() {
+            if parse_line(&self.lines[end - 1]) == Line::Other {
+                end -= 1;
+            } else {
+                break;
+            }
+        }
+        self.lines.drain(start..end);
+        true
+    }
+
+    pub fn has_section(&self, name: &str) -> bool {
+        self.find_section(name).is_some()
+    }
+
+    // Only the header lines are rewritten, so the entries and comments stay as they are.
+    pub fn rename_section(&mut self, name: &str, new_name: &str) -> bool {
+        let mut is_renamed = false;
+        for index in 0..self.lines.len() {
+            if parse_line(&self.lines[index]) == Line::Section(name) {
+                let line = self.with_line_ending(format!("[{}]", new_name), index);
+                self.lines[index] = line;
+                is_renamed = true;
+            }
+        }
+        is_renamed
+    }
+
+    fn find_section(&self, name: &str) -> Option<(usize, usize)> {
+        let start = self
+            .lines
+            .iter()
+            .position(|raw| parse_line(raw) == Line::Section(name))?;
+        let end = (start + 1..self.lines.len())
+            .find(|i| matches!(parse_line(&self.lines[*i]), Line::Section(_)))
+            .unwrap_or(self.lines.len());
+        Some((start, end))
+    }
+
+    fn find_entry(&self, section: &str, key: &str) -> Option<usize> {
+        let (start, end) = self.find_section(section)?;
+        (start + 1..end).find(|i| match parse_line(&self.lines[*i]) {
+            Line::Entry(k, _) => k == key,
+            _ => false,
+        })
+    }
+
+    fn continuation_end(&self, index: usize) -> usize {
+        (index + 1..self.lines.len())
+            .find(|i| parse_line(&self.lines[*i]) != Line::Continuation)
+            .unwrap_or(self.lines.len())
+    }
+
+    fn with_line_ending(&self, line: String, index: usize) -> String {
+        if self.lines[index].ends_with('\r') {
+            line + "\r"
+        } else {
+            line
+        }
+    }
+
+    fn push_line(&mut self, line: String) {
+        if self.crlf && !self.trailing_newline {
+            if let Some(last) = self.lines.last_mut() {
+                last.push('\r');
+            }
+        }
+        if self.crlf {
+            self.lines.push(line + "\r");
+        } else {
+            self.lines.push(line);
+        }
+        self.trailing_newline = true;
+    }
+}
+
+impl Display for IniDocument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.lines.join("\n"))?;
+        if self.trailing_newline {
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+const TEXT: &str = "# managed by hand
+[default]
+region = us-east-1
+output=json
+
+; Familia
+[profile hestia]
+region = ap-northeast-1
+s3 =
+    max_concurrent_requests = 20
+
+# keep this profile
+[profile loki]
+role_arn = arn:aws:iam::000000000000:role/loki
+source_profile = default
+";
+
+#[test]
+fn test_ini_document_parse() {
+    let doc = IniDocument::parse(TEXT);
+    assert_eq!(doc.to_string(), TEXT);
+    assert_eq!(
+        doc.sections(),
+        vec!["default", "profile hestia", "profile loki"]
+    );
+    assert_eq!(doc.get("default", "output"), Some("json".to_string()));
+    assert_eq!(doc.get("profile hestia", "s3"), Some("".to_string()));
+    assert_eq!(doc.get("profile hestia", "max_concurrent_requests"), None);
+    assert_eq!(
+        doc.section("profile loki").unwrap(),
+        vec![
+            (
+                "role_arn".to_string(),
+                "arn:aws:iam::000000000000:role/loki".to_string()
+            ),
+            ("source_profile".to_string(), "default".to_string()),
+        ]
+    );
+
+    let text = "[default]\r\nregion = us-east-1\r\n\r\n# no newline at end\r\n[bell]\r\nregion = us-west-2";
+    assert_eq!(IniDocument::parse(text).to_string(), text);
+    assert_eq!(IniDocument::parse("").to_string(), "");
+}
+
+#[test]
+fn test_ini_document_set() {
+    let mut doc = IniDocument::parse(TEXT);
+    doc.set("profile hestia", "region", "us-west-2");
+    doc.set("profile hestia", "output", "table");
+    doc.set("profile bell", "region", "eu-west-1");
+    let expected = "# managed by hand
+[default]
+region = us-east-1
+output=json
+
+; Familia
+[profile hestia]
+region = us-west-2
+s3 =
+    max_concurrent_requests = 20
+output = table
+
+# keep this profile
+[profile loki]
+role_arn = arn:aws:iam::000000000000:role/loki
+source_profile = default
+
+[profile bell]
+region = eu-west-1
+";
+    assert_eq!(doc.to_string(), expected);
+
+    let mut doc = IniDocument::parse("[default]\r\nregion = us-east-1");
+    doc.set("default", "region", "us-west-2");
+    doc.set("bell", "output", "json");
+    assert_eq!(
+        doc.to_string(),
+        "[default]\r\nregion = us-west-2\r\n\r\n[bell]\r\noutput = json\r\n"
+    );
+}
+
+#[test]
+fn test_ini_document_replace_section() {
+    let mut doc = IniDocument::parse(TEXT);
+    let mut entries: HashMap<String, String> = HashMap::new();
+    entries.insert("output".to_string(), "json".to_string());
+    doc.replace_section("profile hestia", &entries);
+    doc.replace_section("profile bell", &entries);
+    let expected = "# managed by hand
+[default]
+region = us-east-1
+output=json
+
+; Familia
+[profile hestia]
+output = json
+
+# keep this profile
+[profile loki]
+role_arn = arn:aws:iam::000000000000:role/loki
+source_profile = default
+
+[profile bell]
+output = json
+";
+    assert_eq!(doc.to_string(), expected);
+}
+
+#[test]
+fn test_ini_document_remove() {
+    let mut doc = IniDocument::parse(TEXT);
+    assert!(doc.remove("profile hestia", "s3"));
+    assert!(!doc.remove("profile hestia", "s3"));
+    assert!(doc.remove_section("default"));
+    assert!(!doc.remove_section("profile bell"));
+    let expected = "# managed by hand
+
+; Familia
+[profile hestia]
+region = ap-northeast-1
+
+# keep this profile
+[profile loki]
+role_arn = arn:aws:iam::000000000000:role/loki
+source_profile = default
+";
+    assert_eq!(doc.to_string(), expected);
+
+    assert!(doc.remove_section("profile loki"));
+    assert!(doc.remove_section("profile hestia"));
+    assert_eq!(doc.to_string(), "# managed by hand\n\n; Familia\n");
+}
+
+#[test]
+fn test_ini_document_rename() {
+    let mut document = IniDocument::parse(TEXT);
+    assert!(document.rename_section("profile hestia", "profile bell"));
+    assert!(!document.rename_section("profile freya", "profile ouranos"));
+    assert!(!document.has_section("profile hestia"));
+    assert_eq!(
+        document.get("profile bell", "region"),
+        Some("ap-northeast-1".to_string())
+    );
+    assert_eq!(
+        document.to_string(),
+        TEXT.replace("[profile hestia]", "[profile bell]")
+    );
+
+    let mut document = IniDocument::parse("[hestia]\r\nregion = us-east-1\r\n");
+    document.rename_section("hestia", "bell");
+    assert_eq!(document.to_string(), "[bell]\r\nregion = us-east-1\r\n");
+}
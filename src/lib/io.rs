@@ -1,15 +1,21 @@
 // https://github.com/remind101/assume-role/blob/ca1eab460f3267fb7dde8685b0db52a4ea72e35d/main.go
 // https://stackoverflow.com/questions/28370126/how-can-i-test-stdin-and-stdout
 
-use crate::lib::fs::AwsCliOutput;
+use crate::lib::fs::{AwsCliOutput, PRINCIPAL_ARN_KEY, SECURITY_TOKEN_EXPIRES_KEY};
 use rusoto_core::Region;
 use rusoto_sts::{AssumeRoleResponse, Credentials};
+use serde::Serialize;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::io;
 use std::io::Write;
 
 pub trait MasqueradeOutputExt {
+    fn create_environment_variables(
+        &self,
+        output_type: &Option<AwsCliOutput>,
+        default_region: &Option<Region>,
+    ) -> Vec<(String, String)>;
     fn create_bash_credentials(
         &self,
         output_type: &Option<AwsCliOutput>,
@@ -26,42 +32,57 @@ pub trait MasqueradeOutputExt {
         default_region: &Option<Region>,
     ) -> String;
     fn create_shared_credentials(&self) -> HashMap<String, String>;
+    fn create_credential_process_output(&self) -> String;
+}
+
+// https://docs.aws.amazon.com/cli/latest/userguide/cli-configure-sourcing-external.html
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct CredentialProcessOutput<'a> {
+    version: i32,
+    access_key_id: &'a str,
+    secret_access_key: &'a str,
+    session_token: &'a str,
+    expiration: &'a str,
 }
 
 impl MasqueradeOutputExt for Credentials {
-    fn create_bash_credentials(
+    fn create_environment_variables(
         &self,
         output_type: &Option<AwsCliOutput>,
         default_region: &Option<Region>,
-    ) -> String {
-        let args: Vec<String> = std::env::args().collect();
-
-        let mut lines: Vec<String> = Vec::new();
-        lines.push(format!(
-            "export AWS_ACCESS_KEY_ID=\"{}\"",
-            self.access_key_id
-        ));
-        lines.push(format!(
-            "export AWS_SECRET_ACCESS_KEY=\"{}\"",
-            self.secret_access_key
-        ));
-        lines.push(format!(
-            "export AWS_SESSION_TOKEN=\"{}\"",
-            self.session_token
-        ));
-        lines.push(format!(
-            "export AWS_SECURITY_TOKEN=\"{}\"",
-            self.session_token
-        ));
+    ) -> Vec<(String, String)> {
+        let mut vars: Vec<(String, String)> = vec![
+            ("AWS_ACCESS_KEY_ID".to_string(), self.access_key_id.clone()),
+            (
+                "AWS_SECRET_ACCESS_KEY".to_string(),
+                self.secret_access_key.clone(),
+            ),
+            ("AWS_SESSION_TOKEN".to_string(), self.session_token.clone()),
+            ("AWS_SECURITY_TOKEN".to_string(), self.session_token.clone()),
+        ];
         if let Some(output) = output_type {
-            lines.push(format!(
-                "export AWS_DEFAULT_OUTPUT=\"{}\"",
-                output.to_string()
-            ))
+            vars.push(("AWS_DEFAULT_OUTPUT".to_string(), output.to_string()));
         }
         if let Some(region) = default_region {
-            lines.push(format!("export AWS_DEFAULT_REGION=\"{}\"", region.name()))
+            vars.push(("AWS_DEFAULT_REGION".to_string(), region.name().to_string()));
         }
+
+        vars
+    }
+
+    fn create_bash_credentials(
+        &self,
+        output_type: &Option<AwsCliOutput>,
+        default_region: &Option<Region>,
+    ) -> String {
+        let args: Vec<String> = std::env::args().collect();
+
+        let mut lines: Vec<String> = self
+            .create_environment_variables(output_type, default_region)
+            .iter()
+            .map(|(key, value)| format!("export {}=\"{}\"", key, value))
+            .collect();
         lines.push("# Run this to configure your shell:".to_string());
         lines.push(format!("# eval $({})", args.join(" ")));
 
@@ -75,32 +96,11 @@ impl MasqueradeOutputExt for Credentials {
     ) -> String {
         let args: Vec<String> = std::env::args().collect();
 
-        let mut lines: Vec<String> = Vec::new();
-        lines.push(format!(
-            "set -gx AWS_ACCESS_KEY_ID \"{}\"",
-            self.access_key_id
-        ));
-        lines.push(format!(
-            "set -gx AWS_SECRET_ACCESS_KEY \"{}\"",
-            self.secret_access_key
-        ));
-        lines.push(format!(
-            "set -gx AWS_SESSION_TOKEN \"{}\"",
-            self.session_token
-        ));
-        lines.push(format!(
-            "set -gx AWS_SECURITY_TOKEN \"{}\"",
-            self.session_token
-        ));
-        if let Some(output) = output_type {
-            lines.push(format!(
-                "set -gx AWS_DEFAULT_OUTPUT \"{}\"",
-                output.to_string()
-            ))
-        }
-        if let Some(region) = default_region {
-            lines.push(format!("set -gx AWS_DEFAULT_REGION \"{}\"", region.name()))
-        }
+        let mut lines: Vec<String> = self
+            .create_environment_variables(output_type, default_region)
+            .iter()
+            .map(|(key, value)| format!("set -gx {} \"{}\"", key, value))
+            .collect();
         lines.push("# Run this to configure your shell:".to_string());
         lines.push(format!("# eval ({})", args.join(" ")));
 
@@ -114,26 +114,11 @@ impl MasqueradeOutputExt for Credentials {
     ) -> String {
         let args: Vec<String> = std::env::args().collect();
 
-        let mut lines: Vec<String> = Vec::new();
-        lines.push(format!("$env:AWS_ACCESS_KEY_ID=\"{}\"", self.access_key_id));
-        lines.push(format!(
-            "$env:AWS_SECRET_ACCESS_KEY=\"{}\"",
-            self.secret_access_key
-        ));
-        lines.push(format!("$env:AWS_SESSION_TOKEN=\"{}\"", self.session_token));
-        lines.push(format!(
-            "$env:AWS_SECURITY_TOKEN=\"{}\"",
-            self.session_token
-        ));
-        if let Some(output) = output_type {
-            lines.push(format!(
-                "$env:AWS_DEFAULT_OUTPUT=\"{}\"",
-                output.to_string()
-            ))
-        }
-        if let Some(region) = default_region {
-            lines.push(format!("$env:AWS_DEFAULT_REGION=\"{}\"", region.name()))
-        }
+        let mut lines: Vec<String> = self
+            .create_environment_variables(output_type, default_region)
+            .iter()
+            .map(|(key, value)| format!("$env:{}=\"{}\"", key, value))
+            .collect();
         lines.push("# Run this to configure your shell:".to_string());
         lines.push(format!("# {} | Invoke-Expression", args.join(" ")));
 
@@ -159,15 +144,37 @@ impl MasqueradeOutputExt for Credentials {
             (&self.session_token).clone(),
         );
         map.insert(
-            "x_security_token_expires".to_string(),
+            SECURITY_TOKEN_EXPIRES_KEY.to_string(),
             (&self.expiration).clone(),
         );
 
         map
     }
+
+    fn create_credential_process_output(&self) -> String {
+        let output = CredentialProcessOutput {
+            version: 1,
+            access_key_id: &self.access_key_id,
+            secret_access_key: &self.secret_access_key,
+            session_token: &self.session_token,
+            expiration: &self.expiration,
+        };
+        serde_json::to_string(&output).unwrap()
+    }
 }
 
 impl MasqueradeOutputExt for AssumeRoleResponse {
+    fn create_environment_variables(
+        &self,
+        output_type: &Option<AwsCliOutput>,
+        default_region: &Option<Region>,
+    ) -> Vec<(String, String)> {
+        self.credentials
+            .as_ref()
+            .unwrap()
+            .create_environment_variables(output_type, default_region)
+    }
+
     fn create_bash_credentials(
         &self,
         output_type: &Option<AwsCliOutput>,
@@ -208,12 +215,19 @@ impl MasqueradeOutputExt for AssumeRoleResponse {
             .unwrap()
             .create_shared_credentials();
         map.insert(
-            "x_principal_arn".to_string(),
+            PRINCIPAL_ARN_KEY.to_string(),
             (&self.assumed_role_user.as_ref().unwrap().arn).clone(),
         );
 
         map
     }
+
+    fn create_credential_process_output(&self) -> String {
+        self.credentials
+            .as_ref()
+            .unwrap()
+            .create_credential_process_output()
+    }
 }
 
 #[test]
@@ -561,6 +575,22 @@ fn test_credentials_create_power_shell_credentials_4() {
     assert_eq!(actual, expected);
 }
 
+#[test]
+fn test_credentials_create_credential_process_output_1() {
+    let cred = Credentials {
+        access_key_id: "Adventurer (Bell Cranel)".to_string(),
+        expiration: "Monster Festival (Monsterphilia)".to_string(),
+        secret_access_key: "The Blade of a God (Hestia Knife)".to_string(),
+        session_token: "The Weak (Supporter)".to_string(),
+    };
+
+    let actual = cred.create_credential_process_output();
+
+    let expected = r#"{"Version":1,"AccessKeyId":"Adventurer (Bell Cranel)","SecretAccessKey":"The Blade of a God (Hestia Knife)","SessionToken":"The Weak (Supporter)","Expiration":"Monster Festival (Monsterphilia)"}"#;
+
+    assert_eq!(actual, expected);
+}
+
 // https://magidropack.hatenablog.com/entry/2018/12/18/194442
 pub fn get_input<T>(message: T) -> String
 where
@@ -574,6 +604,18 @@ where
     input.trim().to_string()
 }
 
+pub fn get_input_on_stderr<T>(message: T) -> String
+where
+    T: Display,
+{
+    eprint!("{}", message);
+    let mut input = String::new();
+    let _ = io::stderr().flush();
+    io::stdin().read_line(&mut input).ok();
+
+    input.trim().to_string()
+}
+
 pub fn get_confirm_with_default<T>(message: T, default: bool) -> Result<bool, ()>
 where
     T: Display,
@@ -1,5 +1,15 @@
+pub mod arn;
+pub mod cache;
 pub mod cmd_base;
+pub mod diff;
 pub mod dirs;
+pub mod field;
 pub mod fs;
+pub mod ini;
 pub mod io;
+pub mod permission;
+pub mod policy;
+pub mod secret;
+pub mod session;
+pub mod sts;
 pub mod totp;
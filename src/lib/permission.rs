@@ -0,0 +1,103 @@
+use crate::lib::dirs::MASQUERADE_PATH;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+// Files which contain secrets (MFA secrets, session tokens) are readable only by the owner.
+pub const PRIVATE_FILE_MODE: u32 = 0o600;
+pub const PRIVATE_DIR_MODE: u32 = 0o700;
+
+pub fn create_private_dir_all(dir: &Path) -> std::io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(PRIVATE_DIR_MODE);
+    }
+    builder.create(dir)
+}
+
+pub fn create_private_file(path: &Path) -> std::io::Result<File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(PRIVATE_FILE_MODE);
+        let file = options.open(path)?;
+        // The mode above applies only to a new file.
+        file.set_permissions(std::fs::Permissions::from_mode(PRIVATE_FILE_MODE))?;
+        Ok(file)
+    }
+    #[cfg(not(unix))]
+    options.open(path)
+}
+
+pub fn get_private_files() -> Vec<PathBuf> {
+    vec![
+        MASQUERADE_PATH.config().to_path_buf(),
+        MASQUERADE_PATH.cache().to_path_buf(),
+        MASQUERADE_PATH.vault().to_path_buf(),
+        MASQUERADE_PATH.shared_credentials().to_path_buf(),
+    ]
+}
+
+// Returns the mode of the file if it is accessible by the group or others.
+#[cfg(unix)]
+pub fn find_loose_permission(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = std::fs::metadata(path).ok()?.permissions().mode() & 0o777;
+    if mode & 0o077 != 0 {
+        Some(mode)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+pub fn find_loose_permission(_path: &Path) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+pub fn fix_permission(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    let permissions = std::fs::Permissions::from_mode(PRIVATE_FILE_MODE);
+    match std::fs::set_permissions(path, permissions) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!(
+            "failed to change permissions of {}: {}",
+            path.display(),
+            e
+        )),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn fix_permission(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_find_loose_permission() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = std::env::temp_dir().join(format!(
+        "aws-masquerade-permission-test-{}",
+        std::process::id()
+    ));
+    create_private_dir_all(&dir).unwrap();
+    let path = dir.join("credentials");
+
+    create_private_file(&path).unwrap();
+    assert_eq!(find_loose_permission(&path), None);
+
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+    assert_eq!(find_loose_permission(&path), Some(0o644));
+    create_private_file(&path).unwrap();
+    assert_eq!(find_loose_permission(&path), None);
+
+    let mode = std::fs::metadata(&dir).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode, PRIVATE_DIR_MODE);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,89 @@
+use crate::lib::arn::parse_policy_arn;
+use rusoto_sts::PolicyDescriptorType;
+use serde_json::Value;
+
+const FILE_PREFIX: &str = "file://";
+const MAX_POLICY_LENGTH: usize = 2048;
+const MAX_POLICY_ARNS: usize = 10;
+
+// The session policy is given either as inline JSON or as "file://<path>", like the AWS CLI.
+pub fn load_session_policy(source: &str) -> Result<String, String> {
+    let text = if let Some(path) = source.strip_prefix(FILE_PREFIX) {
+        match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => return Err(format!("failed to read session policy file: {}", e)),
+        }
+    } else {
+        source.to_string()
+    };
+    parse_session_policy(&text)
+}
+
+pub fn parse_session_policy(text: &str) -> Result<String, String> {
+    let document: Value = match serde_json::from_str(text) {
+        Ok(document) => document,
+        Err(e) => return Err(format!("failed to parse session policy: {}", e)),
+    };
+    let statement = match document.as_object() {
+        Some(object) => object.get("Statement"),
+        None => return Err("session policy must be a JSON object".to_string()),
+    };
+    match statement {
+        Some(Value::Array(_)) | Some(Value::Object(_)) => (),
+        _ => return Err("session policy must have a \"Statement\"".to_string()),
+    }
+    let packed = document.to_string();
+    if packed.len() > MAX_POLICY_LENGTH {
+        return Err(format!(
+            "session policy must be at most {} characters: {}",
+            MAX_POLICY_LENGTH,
+            packed.len()
+        ));
+    }
+    Ok(packed)
+}
+
+pub fn validate_policy_arn(arn: &str) -> Result<(), String> {
+    parse_policy_arn(arn).map(|_| ())
+}
+
+pub fn create_policy_descriptors(arns: &[String]) -> Result<Vec<PolicyDescriptorType>, String> {
+    if arns.len() > MAX_POLICY_ARNS {
+        return Err(format!(
+            "at most {} policy arns can be passed: {}",
+            MAX_POLICY_ARNS,
+            arns.len()
+        ));
+    }
+    let mut descriptors = Vec::new();
+    for arn in arns {
+        validate_policy_arn(arn)?;
+        descriptors.push(PolicyDescriptorType {
+            arn: Some(arn.clone()),
+        });
+    }
+    Ok(descriptors)
+}
+
+#[test]
+fn test_parse_session_policy() {
+    let text = r#"{
+        "Version": "2012-10-17",
+        "Statement": [{"Effect": "Allow", "Action": "s3:Get*", "Resource": "*"}]
+    }"#;
+    let actual = parse_session_policy(text);
+    let expected = r#"{"Statement":[{"Action":"s3:Get*","Effect":"Allow","Resource":"*"}],"Version":"2012-10-17"}"#;
+    assert_eq!(actual, Ok(expected.to_string()));
+
+    assert!(parse_session_policy("{").is_err());
+    assert!(parse_session_policy("[]").is_err());
+    assert!(parse_session_policy(r#"{"Version": "2012-10-17"}"#).is_err());
+}
+
+#[test]
+fn test_validate_policy_arn() {
+    assert!(validate_policy_arn("arn:aws:iam::aws:policy/ReadOnlyAccess").is_ok());
+    assert!(validate_policy_arn("arn:aws-cn:iam::000000000000:policy/path/to/Policy").is_ok());
+    assert!(validate_policy_arn("arn:aws:iam::000000000000:role/Role").is_err());
+    assert!(validate_policy_arn("ReadOnlyAccess").is_err());
+}
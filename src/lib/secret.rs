@@ -0,0 +1,355 @@
+use crate::lib::dirs::MASQUERADE_PATH;
+use crate::lib::fs::{write_private_file_atomically, Account};
+use crate::lib::permission::create_private_dir_all;
+use data_encoding::BASE64;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{aead, pbkdf2};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::num::NonZeroU32;
+
+const KEYRING_SERVICE: &str = "aws-masquerade";
+pub const VAULT_PASSPHRASE_ENV_NAME: &str = "AWS_MASQUERADE_VAULT_PASSPHRASE";
+const VAULT_ITERATIONS: u32 = 100_000;
+const SALT_LENGTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SecretStorage {
+    Plaintext,
+    Keyring,
+    Vault,
+}
+
+impl SecretStorage {
+    pub fn to_str(&self) -> &str {
+        match self {
+            SecretStorage::Plaintext => "plaintext",
+            SecretStorage::Keyring => "keyring",
+            SecretStorage::Vault => "vault",
+        }
+    }
+
+    pub fn from_str(v: &str) -> Result<SecretStorage, String> {
+        match v {
+            "plaintext" => Ok(SecretStorage::Plaintext),
+            "keyring" => Ok(SecretStorage::Keyring),
+            "vault" => Ok(SecretStorage::Vault),
+            _ => Err(format!("invalid secret storage: {}", v)),
+        }
+    }
+}
+
+impl Display for SecretStorage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_str())
+    }
+}
+
+// The config refers to a secret outside of it as "<storage>:<id>" (e.g. "keyring:hestia").
+pub fn create_secret_ref(storage: &SecretStorage, id: &str) -> String {
+    format!("{}:{}", storage, id)
+}
+
+pub fn parse_secret_ref(reference: &str) -> Result<(SecretStorage, &str), String> {
+    let (storage, id) = match reference.find(':') {
+        Some(index) => (&reference[..index], &reference[index + 1..]),
+        None => return Err(format!("invalid secret reference: {}", reference)),
+    };
+    match SecretStorage::from_str(storage)? {
+        SecretStorage::Plaintext => Err(format!("invalid secret reference: {}", reference)),
+        _ if id.is_empty() => Err(format!("invalid secret reference: {}", reference)),
+        storage => Ok((storage, id)),
+    }
+}
+
+pub fn store_secret(reference: &str, secret: &str) -> Result<(), String> {
+    match parse_secret_ref(reference)? {
+        (SecretStorage::Keyring, id) => match create_keyring_entry(id)?.set_password(secret) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("failed to store secret in keyring: {}", e)),
+        },
+        (_, id) => {
+            let mut vault = load_vault()?.unwrap_or_else(Vault::new);
+            let passphrase = get_vault_passphrase(vault.entries.is_empty())?;
+            vault.verify(&passphrase)?;
+            vault.encrypt(&passphrase, id, secret)?;
+            save_vault(&vault)
+        }
+    }
+}
+
+pub fn load_secret(reference: &str) -> Result<String, String> {
+    match parse_secret_ref(reference)? {
+        (SecretStorage::Keyring, id) => match create_keyring_entry(id)?.get_password() {
+            Ok(secret) => Ok(secret),
+            Err(e) => Err(format!("failed to load secret from keyring: {}", e)),
+        },
+        (_, id) => match load_vault()? {
+            Some(vault) => vault.decrypt(&get_vault_passphrase(false)?, id),
+            None => Err("vault does not exist".to_string()),
+        },
+    }
+}
+
+pub fn delete_secret(reference: &str) -> Result<(), String> {
+    match parse_secret_ref(reference)? {
+        (SecretStorage::Keyring, id) => match create_keyring_entry(id)?.delete_password() {
+            Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!("failed to delete secret from keyring: {}", e)),
+        },
+        (_, id) => match load_vault()? {
+            Some(mut vault) => {
+                if vault.entries.remove(id).is_some() {
+                    save_vault(&vault)?;
+                }
+                Ok(())
+            }
+            None => Ok(()),
+        },
+    }
+}
+
+// Stores the secret again under the new id in the same storage, and returns the new reference.
+pub fn copy_secret(reference: &str, new_id: &str) -> Result<String, String> {
+    let (storage, _) = parse_secret_ref(reference)?;
+    let new_reference = create_secret_ref(&storage, new_id);
+    let secret = load_secret(reference)?;
+    store_secret(&new_reference, &secret)?;
+    Ok(new_reference)
+}
+
+pub fn resolve_mfa_secret(account: &Account) -> Result<Option<String>, String> {
+    if let Some(secret) = &account.mfa_secret {
+        return Ok(Some(secret.clone()));
+    }
+    match &account.mfa_secret_ref {
+        Some(reference) => load_secret(reference).map(Some),
+        None => Ok(None),
+    }
+}
+
+// A secret entered in the wizard is kept in mfa_secret until the account is saved,
+// then it is moved to the storage which mfa_secret_ref refers to.
+pub fn store_pending_mfa_secret(account: &mut Account) -> Result<(), String> {
+    if let (Some(secret), Some(reference)) = (&account.mfa_secret, &account.mfa_secret_ref) {
+        store_secret(reference, secret)?;
+        account.mfa_secret = None;
+    }
+    Ok(())
+}
+
+fn create_keyring_entry(id: &str) -> Result<keyring::Entry, String> {
+    match keyring::Entry::new(KEYRING_SERVICE, id) {
+        Ok(entry) => Ok(entry),
+        Err(e) => Err(format!("failed to open keyring: {}", e)),
+    }
+}
+
+// The passphrase is read from the environment first, so that the vault works without a terminal.
+fn get_vault_passphrase(is_new: bool) -> Result<String, String> {
+    if let Ok(passphrase) = std::env::var(VAULT_PASSPHRASE_ENV_NAME) {
+        return Ok(passphrase);
+    }
+    let passphrase = match rpassword::prompt_password("Vault passphrase: ") {
+        Ok(passphrase) => passphrase,
+        Err(e) => return Err(format!("failed to read vault passphrase: {}", e)),
+    };
+    if is_new {
+        match rpassword::prompt_password("Confirm vault passphrase: ") {
+            Ok(confirmed) if confirmed == passphrase => (),
+            Ok(_) => return Err("vault passphrases do not match".to_string()),
+            Err(e) => return Err(format!("failed to read vault passphrase: {}", e)),
+        }
+    }
+    Ok(passphrase)
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Vault {
+    salt: String,
+    iterations: u32,
+    entries: BTreeMap<String, VaultEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VaultEntry {
+    nonce: String,
+    ciphertext: String,
+}
+
+impl Vault {
+    pub fn new() -> Vault {
+        let mut salt = [0u8; SALT_LENGTH];
+        SystemRandom::new().fill(&mut salt).unwrap();
+        Vault {
+            salt: BASE64.encode(&salt),
+            iterations: VAULT_ITERATIONS,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    fn derive_key(&self, passphrase: &str) -> Result<aead::LessSafeKey, String> {
+        let salt = match BASE64.decode(self.salt.as_bytes()) {
+            Ok(salt) => salt,
+            Err(e) => return Err(format!("failed to decode salt of vault: {}", e)),
+        };
+        let iterations = match NonZeroU32::new(self.iterations) {
+            Some(iterations) => iterations,
+            None => return Err("iterations of vault must not be zero".to_string()),
+        };
+        let mut key = [0u8; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            iterations,
+            &salt,
+            passphrase.as_bytes(),
+            &mut key,
+        );
+        let key = aead::UnboundKey::new(&aead::AES_256_GCM, &key).unwrap();
+        Ok(aead::LessSafeKey::new(key))
+    }
+
+    // All entries share the passphrase, so a new one is checked against an existing entry.
+    pub fn verify(&self, passphrase: &str) -> Result<(), String> {
+        match self.entries.keys().next() {
+            Some(id) => self.decrypt(passphrase, id).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn encrypt(&mut self, passphrase: &str, id: &str, secret: &str) -> Result<(), String> {
+        let key = self.derive_key(passphrase)?;
+        let mut nonce = [0u8; aead::NONCE_LEN];
+        SystemRandom::new().fill(&mut nonce).unwrap();
+        let mut in_out = secret.as_bytes().to_vec();
+        if key
+            .seal_in_place_append_tag(
+                aead::Nonce::assume_unique_for_key(nonce),
+                aead::Aad::from(id.as_bytes()),
+                &mut in_out,
+            )
+            .is_err()
+        {
+            return Err("failed to encrypt secret".to_string());
+        }
+        self.entries.insert(
+            id.to_string(),
+            VaultEntry {
+                nonce: BASE64.encode(&nonce),
+                ciphertext: BASE64.encode(&in_out),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn decrypt(&self, passphrase: &str, id: &str) -> Result<String, String> {
+        let entry = match self.entries.get(id) {
+            Some(entry) => entry,
+            None => return Err(format!("secret \"{}\" does not exist in vault", id)),
+        };
+        let key = self.derive_key(passphrase)?;
+        let nonce = match BASE64.decode(entry.nonce.as_bytes()) {
+            Ok(nonce) if nonce.len() == aead::NONCE_LEN => {
+                let mut bytes = [0u8; aead::NONCE_LEN];
+                bytes.copy_from_slice(&nonce);
+                aead::Nonce::assume_unique_for_key(bytes)
+            }
+            _ => return Err(format!("invalid nonce of secret \"{}\" in vault", id)),
+        };
+        let mut in_out = match BASE64.decode(entry.ciphertext.as_bytes()) {
+            Ok(ciphertext) => ciphertext,
+            Err(e) => return Err(format!("failed to decode secret \"{}\": {}", id, e)),
+        };
+        let plain = match key.open_in_place(nonce, aead::Aad::from(id.as_bytes()), &mut in_out) {
+            Ok(plain) => plain,
+            Err(_) => return Err("failed to decrypt vault (wrong passphrase?)".to_string()),
+        };
+        match String::from_utf8(plain.to_vec()) {
+            Ok(secret) => Ok(secret),
+            Err(e) => Err(format!("failed to decode secret \"{}\": {}", id, e)),
+        }
+    }
+}
+
+pub fn load_vault() -> Result<Option<Vault>, String> {
+    let path = MASQUERADE_PATH.vault();
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => return Err(format!("failed to read vault: {}", e)),
+    };
+    match serde_json::from_str(&text) {
+        Ok(vault) => Ok(Some(vault)),
+        Err(e) => Err(format!("failed to parse vault: {}", e)),
+    }
+}
+
+pub fn save_vault(vault: &Vault) -> Result<(), String> {
+    let path = MASQUERADE_PATH.vault();
+    let dir = path.parent().unwrap();
+    match create_private_dir_all(dir) {
+        Ok(_) => (),
+        Err(e) => return Err(format!("failed to create config directory: {}", e)),
+    };
+    let text = match serde_json::to_string_pretty(vault) {
+        Ok(text) => text,
+        Err(e) => return Err(format!("failed to serialize vault: {}", e)),
+    };
+    match write_private_file_atomically(path, &text) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("failed to write vault: {}", e)),
+    }
+}
+
+#[test]
+fn test_parse_secret_ref() {
+    assert_eq!(
+        parse_secret_ref("keyring:hestia"),
+        Ok((SecretStorage::Keyring, "hestia"))
+    );
+    assert_eq!(
+        parse_secret_ref("vault:bell:cranel"),
+        Ok((SecretStorage::Vault, "bell:cranel"))
+    );
+    assert!(parse_secret_ref("plaintext:hestia").is_err());
+    assert!(parse_secret_ref("vault:").is_err());
+    assert!(parse_secret_ref("hestia").is_err());
+}
+
+#[test]
+fn test_vault() {
+    let mut vault = Vault::new();
+    vault.iterations = 1;
+    let secret = "SDI7UGDNQ5NURIUPBOWEUTHIDBIT6DRHR4WLWS7N7C3C6VS3LJKNWHL2JZIFIUYI";
+
+    vault.encrypt("Hestia Knife", "hestia", secret).unwrap();
+    vault.encrypt("Hestia Knife", "bell", "Argonaut").unwrap();
+
+    let text = serde_json::to_string(&vault).unwrap();
+    assert!(!text.contains(secret));
+    let vault: Vault = serde_json::from_str(&text).unwrap();
+    assert_eq!(
+        vault.decrypt("Hestia Knife", "hestia"),
+        Ok(secret.to_string())
+    );
+    assert_eq!(
+        vault.decrypt("Hestia Knife", "bell"),
+        Ok("Argonaut".to_string())
+    );
+    assert!(vault.verify("Hestia Knife").is_ok());
+    assert!(vault.verify("Goddess Knife").is_err());
+    assert!(vault.decrypt("Goddess Knife", "hestia").is_err());
+    assert!(vault.decrypt("Hestia Knife", "loki").is_err());
+
+    // The ciphertext is bound to its id.
+    let mut swapped = vault.clone();
+    let entry = swapped.entries["bell"].clone();
+    swapped.entries.insert("hestia".to_string(), entry);
+    assert!(swapped.decrypt("Hestia Knife", "hestia").is_err());
+}
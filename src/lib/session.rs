@@ -0,0 +1,300 @@
+use regex::Regex;
+use rusoto_sts::Tag;
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_ROLE_SESSION_NAME: &str = "{user}@{hostname}";
+pub const DEFAULT_SOURCE_IDENTITY: &str = "{user}";
+const MIN_ROLE_SESSION_NAME_LENGTH: usize = 2;
+const MAX_ROLE_SESSION_NAME_LENGTH: usize = 64;
+const MIN_EXTERNAL_ID_LENGTH: usize = 2;
+const MAX_EXTERNAL_ID_LENGTH: usize = 1224;
+const MAX_SESSION_TAGS: usize = 50;
+const MAX_TAG_KEY_LENGTH: usize = 128;
+const MAX_TAG_VALUE_LENGTH: usize = 256;
+
+lazy_static! {
+    static ref RE_PLACEHOLDER: Regex = Regex::new(r"\{([^{}]*)\}").unwrap();
+    static ref RE_INVALID_CHARS: Regex = Regex::new(r"[^A-Za-z0-9_+=,.@-]").unwrap();
+    static ref RE_ROLE_SESSION_NAME: Regex = Regex::new(r"^[A-Za-z0-9_+=,.@-]+$").unwrap();
+    static ref RE_EXTERNAL_ID: Regex = Regex::new(r"^[A-Za-z0-9_+=,.@:/-]+$").unwrap();
+    static ref RE_TAG: Regex = Regex::new(r"^[\p{L}\p{Z}\p{N}_.:/=+\-@]*$").unwrap();
+}
+
+pub struct SessionNameContext {
+    pub user: String,
+    pub hostname: String,
+    pub account: String,
+    pub timestamp: u64,
+}
+
+impl SessionNameContext {
+    pub fn new(account_name: &str) -> SessionNameContext {
+        SessionNameContext {
+            user: whoami::username(),
+            hostname: whoami::fallible::hostname().unwrap_or_else(|_| "localhost".to_string()),
+            account: account_name.to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }
+    }
+
+    fn example() -> SessionNameContext {
+        SessionNameContext {
+            user: "user".to_string(),
+            hostname: "hostname".to_string(),
+            account: "account".to_string(),
+            timestamp: 0,
+        }
+    }
+
+    fn resolve(&self, placeholder: &str) -> Result<String, String> {
+        match placeholder {
+            "user" => Ok(self.user.clone()),
+            "hostname" => Ok(self.hostname.clone()),
+            "account" => Ok(self.account.clone()),
+            "timestamp" => Ok(self.timestamp.to_string()),
+            "uuid" => Ok(uuid::Uuid::new_v4().to_string()),
+            _ => Err(format!(
+                "unknown placeholder in template: {{{}}}",
+                placeholder
+            )),
+        }
+    }
+}
+
+pub fn validate_role_session_name(name: &str) -> Result<(), String> {
+    if name.len() < MIN_ROLE_SESSION_NAME_LENGTH || name.len() > MAX_ROLE_SESSION_NAME_LENGTH {
+        return Err(format!(
+            "role session name must be {} to {} characters: {}",
+            MIN_ROLE_SESSION_NAME_LENGTH, MAX_ROLE_SESSION_NAME_LENGTH, name
+        ));
+    }
+    if !RE_ROLE_SESSION_NAME.is_match(name) {
+        return Err(format!(
+            "role session name may only contain alphanumerics and =,.@_+-: {}",
+            name
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_role_session_name_template(template: &str) -> Result<(), String> {
+    render_role_session_name(template, &SessionNameContext::example()).map(|_| ())
+}
+
+pub fn validate_source_identity(source_identity: &str) -> Result<(), String> {
+    if source_identity.len() < MIN_ROLE_SESSION_NAME_LENGTH
+        || source_identity.len() > MAX_ROLE_SESSION_NAME_LENGTH
+    {
+        return Err(format!(
+            "source identity must be {} to {} characters: {}",
+            MIN_ROLE_SESSION_NAME_LENGTH, MAX_ROLE_SESSION_NAME_LENGTH, source_identity
+        ));
+    }
+    if !RE_ROLE_SESSION_NAME.is_match(source_identity) {
+        return Err(format!(
+            "source identity may only contain alphanumerics and =,.@_+-: {}",
+            source_identity
+        ));
+    }
+    if source_identity.to_lowercase().starts_with("aws:") {
+        return Err(format!(
+            "source identity must not start with \"aws:\": {}",
+            source_identity
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_source_identity_template(template: &str) -> Result<(), String> {
+    render_source_identity(template, &SessionNameContext::example()).map(|_| ())
+}
+
+pub fn validate_external_id(external_id: &str) -> Result<(), String> {
+    if external_id.len() < MIN_EXTERNAL_ID_LENGTH || external_id.len() > MAX_EXTERNAL_ID_LENGTH {
+        return Err(format!(
+            "external id must be {} to {} characters",
+            MIN_EXTERNAL_ID_LENGTH, MAX_EXTERNAL_ID_LENGTH
+        ));
+    }
+    if !RE_EXTERNAL_ID.is_match(external_id) {
+        return Err("external id may only contain alphanumerics and =,.@:/_+-".to_string());
+    }
+    Ok(())
+}
+
+pub fn parse_session_tag(text: &str) -> Result<(String, String), String> {
+    let mut parts = text.splitn(2, '=');
+    let key = parts.next().unwrap_or("").trim();
+    let value = match parts.next() {
+        Some(value) => value.trim(),
+        None => return Err(format!("session tag must be key=value: {}", text)),
+    };
+    validate_session_tag(key, value)?;
+    Ok((key.to_string(), value.to_string()))
+}
+
+pub fn validate_session_tag(key: &str, value: &str) -> Result<(), String> {
+    if key.is_empty() || key.chars().count() > MAX_TAG_KEY_LENGTH || !RE_TAG.is_match(key) {
+        return Err(format!("invalid session tag key: {}", key));
+    }
+    if value.chars().count() > MAX_TAG_VALUE_LENGTH || !RE_TAG.is_match(value) {
+        return Err(format!("invalid session tag value: {}", value));
+    }
+    Ok(())
+}
+
+pub fn create_session_tags(
+    tags: &BTreeMap<String, String>,
+    transitive_tag_keys: &[String],
+) -> Result<Vec<Tag>, String> {
+    if tags.len() > MAX_SESSION_TAGS {
+        return Err(format!(
+            "at most {} session tags can be passed: {}",
+            MAX_SESSION_TAGS,
+            tags.len()
+        ));
+    }
+    for key in transitive_tag_keys {
+        if !tags.contains_key(key) {
+            return Err(format!("transitive tag key is not a session tag: {}", key));
+        }
+    }
+    let mut result = Vec::new();
+    for (key, value) in tags {
+        validate_session_tag(key, value)?;
+        result.push(Tag {
+            key: key.clone(),
+            value: value.clone(),
+        });
+    }
+    Ok(result)
+}
+
+// Values substituted into placeholders are sanitized so that e.g. "DOMAIN\user" still yields a
+// valid name, while invalid characters written in the template itself are reported as errors.
+fn expand_template(template: &str, context: &SessionNameContext) -> Result<String, String> {
+    let mut name = String::new();
+    let mut last = 0;
+    for caps in RE_PLACEHOLDER.captures_iter(template) {
+        let whole = caps.get(0).unwrap();
+        name.push_str(&template[last..whole.start()]);
+        let value = context.resolve(&caps[1])?;
+        name.push_str(&RE_INVALID_CHARS.replace_all(&value, "-"));
+        last = whole.end();
+    }
+    name.push_str(&template[last..]);
+
+    Ok(name.chars().take(MAX_ROLE_SESSION_NAME_LENGTH).collect())
+}
+
+pub fn render_role_session_name(
+    template: &str,
+    context: &SessionNameContext,
+) -> Result<String, String> {
+    let name = expand_template(template, context)?;
+    validate_role_session_name(&name)?;
+    Ok(name)
+}
+
+pub fn render_source_identity(
+    template: &str,
+    context: &SessionNameContext,
+) -> Result<String, String> {
+    let source_identity = expand_template(template, context)?;
+    validate_source_identity(&source_identity)?;
+    Ok(source_identity)
+}
+
+#[test]
+fn test_render_role_session_name_1() {
+    let context = SessionNameContext {
+        user: "bell".to_string(),
+        hostname: "hestia-familia.local".to_string(),
+        account: "orario".to_string(),
+        timestamp: 1600000000,
+    };
+    let actual = render_role_session_name("{user}@{hostname}", &context);
+    assert_eq!(actual, Ok("bell@hestia-familia.local".to_string()));
+
+    let actual = render_role_session_name("{account}-{user}-{timestamp}", &context);
+    assert_eq!(actual, Ok("orario-bell-1600000000".to_string()));
+}
+
+#[test]
+fn test_render_role_session_name_2() {
+    let context = SessionNameContext {
+        user: "DUNGEON\\Bell Cranel".to_string(),
+        hostname: "x".repeat(100),
+        account: "orario".to_string(),
+        timestamp: 0,
+    };
+    let actual = render_role_session_name("{user}", &context);
+    assert_eq!(actual, Ok("DUNGEON-Bell-Cranel".to_string()));
+
+    let actual = render_role_session_name("{hostname}", &context).unwrap();
+    assert_eq!(actual.len(), 64);
+
+    assert!(render_role_session_name("{unknown}", &context).is_err());
+    assert!(render_role_session_name("bell cranel", &context).is_err());
+    assert!(render_role_session_name("b", &context).is_err());
+}
+
+#[test]
+fn test_validate_external_id() {
+    assert!(validate_external_id("loki-familia:12345/abc").is_ok());
+    assert!(validate_external_id("a").is_err());
+    assert!(validate_external_id("freya familia").is_err());
+    assert!(validate_external_id(&"x".repeat(1225)).is_err());
+}
+
+#[test]
+fn test_parse_session_tag() {
+    assert_eq!(
+        parse_session_tag("Project=Xenos"),
+        Ok(("Project".to_string(), "Xenos".to_string()))
+    );
+    assert_eq!(
+        parse_session_tag("CostCenter=a=b"),
+        Ok(("CostCenter".to_string(), "a=b".to_string()))
+    );
+    assert_eq!(
+        parse_session_tag("Empty="),
+        Ok(("Empty".to_string(), "".to_string()))
+    );
+    assert!(parse_session_tag("Project").is_err());
+    assert!(parse_session_tag("=Xenos").is_err());
+    assert!(parse_session_tag("Pro*ject=Xenos").is_err());
+}
+
+#[test]
+fn test_create_session_tags() {
+    let mut tags = BTreeMap::new();
+    tags.insert("Project".to_string(), "Xenos".to_string());
+    tags.insert("Team".to_string(), "Hestia".to_string());
+
+    let actual = create_session_tags(&tags, &["Team".to_string()]).unwrap();
+    assert_eq!(actual.len(), 2);
+    assert_eq!(actual[0].key, "Project");
+    assert_eq!(actual[1].value, "Hestia");
+
+    assert!(create_session_tags(&tags, &["Familia".to_string()]).is_err());
+}
+
+#[test]
+fn test_render_source_identity() {
+    let context = SessionNameContext {
+        user: "bell".to_string(),
+        hostname: "hestia-familia.local".to_string(),
+        account: "orario".to_string(),
+        timestamp: 0,
+    };
+    let actual = render_source_identity(DEFAULT_SOURCE_IDENTITY, &context);
+    assert_eq!(actual, Ok("bell".to_string()));
+
+    assert!(render_source_identity("aws:{user}", &context).is_err());
+    assert!(render_source_identity("{user} cranel", &context).is_err());
+}
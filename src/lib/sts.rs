@@ -0,0 +1,175 @@
+use rusoto_core::credential::{AwsCredentials, ProvideAwsCredentials};
+use rusoto_core::request::DispatchSignedRequestFuture;
+use rusoto_core::signature::{SignedRequest, SignedRequestPayload};
+use rusoto_core::{DispatchSignedRequest, HttpClient, Region};
+use std::time::Duration;
+
+pub const STS_REGION_ARG_NAME: &str = "sts_region";
+pub const STS_ENDPOINT_ARG_NAME: &str = "sts_endpoint";
+
+// Without a region, the STS of the partition of the role is used.
+pub fn default_sts_region(partition: &str) -> Region {
+    match partition {
+        "aws-cn" => Region::CnNorth1,
+        "aws-us-gov" => Region::UsGovWest1,
+        _ => Region::UsEast1,
+    }
+}
+
+pub fn create_sts_region(
+    region: Option<Region>,
+    endpoint: Option<String>,
+    partition: &str,
+) -> Region {
+    let region = region.unwrap_or_else(|| default_sts_region(partition));
+    match endpoint {
+        Some(endpoint) => Region::Custom {
+            name: region.name().to_string(),
+            endpoint,
+        },
+        None => region,
+    }
+}
+
+pub fn validate_sts_endpoint(endpoint: &str) -> Result<(), String> {
+    let rest = if let Some(rest) = endpoint.strip_prefix("https://") {
+        rest
+    } else if let Some(rest) = endpoint.strip_prefix("http://") {
+        rest
+    } else {
+        return Err(format!(
+            "sts endpoint must start with http:// or https://: {}",
+            endpoint
+        ));
+    };
+    if rest.is_empty() || rest.starts_with('/') {
+        return Err(format!("sts endpoint must have a host: {}", endpoint));
+    }
+    Ok(())
+}
+
+// rusoto_sts 0.45 does not know the SourceIdentity parameter of AssumeRole,
+// so it is appended to the serialized request which is then signed again.
+pub struct SourceIdentityDispatcher {
+    inner: HttpClient,
+    credentials: AwsCredentials,
+    source_identity: String,
+}
+
+impl SourceIdentityDispatcher {
+    pub fn new(
+        inner: HttpClient,
+        credentials: AwsCredentials,
+        source_identity: &str,
+    ) -> SourceIdentityDispatcher {
+        SourceIdentityDispatcher {
+            inner,
+            credentials,
+            source_identity: source_identity.to_string(),
+        }
+    }
+}
+
+impl DispatchSignedRequest for SourceIdentityDispatcher {
+    fn dispatch(
+        &self,
+        mut request: SignedRequest,
+        timeout: Option<Duration>,
+    ) -> DispatchSignedRequestFuture {
+        let payload = match &request.payload {
+            Some(SignedRequestPayload::Buffer(payload)) if is_assume_role(payload) => Some(
+                append_param(payload, "SourceIdentity", &self.source_identity),
+            ),
+            _ => None,
+        };
+        if let Some(payload) = payload {
+            request.set_payload(Some(payload));
+            request.sign(&self.credentials);
+        }
+        self.inner.dispatch(request, timeout)
+    }
+}
+
+fn is_assume_role(payload: &[u8]) -> bool {
+    payload
+        .split(|b| *b == b'&')
+        .any(|param| param == b"Action=AssumeRole")
+}
+
+fn append_param(payload: &[u8], key: &str, value: &str) -> Vec<u8> {
+    let mut result = payload.to_vec();
+    result.extend(format!("&{}={}", key, encode_form_value(value)).into_bytes());
+    result
+}
+
+fn encode_form_value(value: &str) -> String {
+    let mut result = String::new();
+    for b in value.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'*' => {
+                result.push(b as char)
+            }
+            b' ' => result.push('+'),
+            _ => result.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    result
+}
+
+pub fn resolve_credentials<P>(provider: P) -> Result<AwsCredentials, String>
+where
+    P: ProvideAwsCredentials,
+{
+    let mut runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => return Err(format!("failed to create async runtime: {}", e)),
+    };
+    match runtime.block_on(provider.credentials()) {
+        Ok(credentials) => Ok(credentials),
+        Err(e) => Err(format!("failed to load source credentials: {}", e)),
+    }
+}
+
+#[test]
+fn test_create_sts_region() {
+    assert_eq!(create_sts_region(None, None, "aws"), Region::UsEast1);
+    assert_eq!(create_sts_region(None, None, "aws-cn"), Region::CnNorth1);
+    assert_eq!(
+        create_sts_region(None, None, "aws-us-gov"),
+        Region::UsGovWest1
+    );
+    assert_eq!(
+        create_sts_region(Some(Region::CnNorth1), None, "aws"),
+        Region::CnNorth1
+    );
+    assert_eq!(
+        create_sts_region(
+            Some(Region::ApNortheast1),
+            Some("http://localhost:5000".to_string()),
+            "aws"
+        ),
+        Region::Custom {
+            name: "ap-northeast-1".to_string(),
+            endpoint: "http://localhost:5000".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_validate_sts_endpoint() {
+    assert!(validate_sts_endpoint("https://sts.ap-northeast-1.amazonaws.com").is_ok());
+    assert!(validate_sts_endpoint("http://localhost:5000").is_ok());
+    assert!(validate_sts_endpoint("sts.amazonaws.com").is_err());
+    assert!(validate_sts_endpoint("https://").is_err());
+}
+
+#[test]
+fn test_append_param() {
+    let payload = b"Action=AssumeRole&RoleArn=arn%3Aaws%3Aiam%3A%3A000000000000%3Arole%2Fhestia";
+    assert!(is_assume_role(payload));
+    assert!(!is_assume_role(b"Action=AssumeRoleWithSAML"));
+
+    let actual = append_param(b"Action=AssumeRole", "SourceIdentity", "bell+cranel@orario");
+    let expected = b"Action=AssumeRole&SourceIdentity=bell%2Bcranel%40orario".to_vec();
+    assert_eq!(actual, expected);
+}
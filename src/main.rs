@@ -16,22 +16,40 @@ fn main() {
         .version(crate_version!())
         .subcommand(cmd::add::Add::subcommand())
         .subcommand(cmd::assume::Assume::subcommand())
+        .subcommand(cmd::exec::Exec::subcommand())
+        .subcommand(cmd::whoami::Whoami::subcommand())
         .subcommand(cmd::list::List::subcommand())
         .subcommand(cmd::view::View::subcommand())
         .subcommand(cmd::edit::Edit::subcommand())
+        .subcommand(cmd::set::Set::subcommand())
+        .subcommand(cmd::unset::Unset::subcommand())
+        .subcommand(cmd::rename::Rename::subcommand())
+        .subcommand(cmd::clone::CloneAccount::subcommand())
         .subcommand(cmd::remove::Remove::subcommand())
         .subcommand(cmd::validate::Validate::subcommand())
+        .subcommand(cmd::sync_profiles::SyncProfiles::subcommand())
+        .subcommand(cmd::clean::Clean::subcommand())
+        .subcommand(cmd::status::Status::subcommand())
         .subcommand(cmd::config_path::ConfigPath::subcommand())
         .get_matches();
 
     let result = match matches.subcommand() {
         (cmd::add::NAME, Some(arg)) => cmd::add::Add::run(&arg),
         (cmd::assume::NAME, Some(arg)) => cmd::assume::Assume::run(&arg),
+        (cmd::exec::NAME, Some(args)) => cmd::exec::Exec::run(args),
+        (cmd::whoami::NAME, Some(args)) => cmd::whoami::Whoami::run(args),
         (cmd::list::NAME, Some(args)) => cmd::list::List::run(&args),
         (cmd::view::NAME, Some(args)) => cmd::view::View::run(&args),
         (cmd::edit::NAME, Some(args)) => cmd::edit::Edit::run(&args),
+        (cmd::set::NAME, Some(args)) => cmd::set::Set::run(args),
+        (cmd::unset::NAME, Some(args)) => cmd::unset::Unset::run(args),
+        (cmd::rename::NAME, Some(args)) => cmd::rename::Rename::run(args),
+        (cmd::clone::NAME, Some(args)) => cmd::clone::CloneAccount::run(args),
         (cmd::remove::NAME, Some(args)) => cmd::remove::Remove::run(&args),
         (cmd::validate::NAME, Some(args)) => cmd::validate::Validate::run(&args),
+        (cmd::sync_profiles::NAME, Some(args)) => cmd::sync_profiles::SyncProfiles::run(args),
+        (cmd::clean::NAME, Some(args)) => cmd::clean::Clean::run(args),
+        (cmd::status::NAME, Some(args)) => cmd::status::Status::run(args),
         (cmd::config_path::NAME, Some(args)) => cmd::config_path::ConfigPath::run(&args),
         _ => Err("No subcommand chosen. Add --help | -h to view the subcommands.".to_string()),
     };